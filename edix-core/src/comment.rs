@@ -0,0 +1,72 @@
+/*
+
+Comment toggling:
+- comment markers come from the selected syntax's metadata (`TM_COMMENT_START` / `TM_COMMENT_END`
+  shell variables), a start marker without end marker is a line comment
+- line comments are preferred, lines are commented at the smallest indentation of the non-blank
+  lines, and uncommented if all non-blank lines are commented already
+- syntaxes with block comments only (e.g. CSS, HTML) wrap the text in a single block comment
+- markers are inserted as defined (e.g. "# " including the space), but found without the
+  surrounding whitespace when uncommenting
+
+*/
+
+use crate::content::RichContentOptions;
+use crate::highlight::syntax_metadata;
+use crate::indent::leading_whitespace;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommentMarkers {
+    Line(String),
+    Block(String, String),
+}
+
+pub fn comment_markers(options: &RichContentOptions) -> Option<CommentMarkers> {
+    let metadata = syntax_metadata(options);
+    if let Some(start) = metadata.line_comment() {
+        return Some(CommentMarkers::Line(start.to_string()));
+    }
+    metadata.block_comment().map(|(start, end)| CommentMarkers::Block(start.to_string(), end.to_string()))
+}
+
+// comments or uncomments all non-blank `lines` with the line comment `marker`
+pub fn toggle_line_comments(lines: &[String], marker: &str) -> Vec<String> {
+    let trimmed = marker.trim();
+    let non_blank = || lines.iter().filter(|l| !l.trim().is_empty());
+    let commented = non_blank().all(|l| l.trim_start().starts_with(trimmed));
+    let indent = non_blank().map(|l| leading_whitespace(l).chars().count()).min().unwrap_or(0);
+    lines.iter().map(|line| {
+        if line.trim().is_empty() {
+            line.clone()
+        } else if commented {
+            let (indent, text) = line.split_at(leading_whitespace(line).len());
+            let rest = text.strip_prefix(trimmed).unwrap_or(text);
+            let rest = if marker.ends_with(' ') { rest.strip_prefix(' ').unwrap_or(rest) } else { rest };
+            format!("{}{}", indent, rest)
+        } else {
+            let (indent, text) = line.split_at(line.char_indices().nth(indent).map_or(line.len(), |(i, _)| i));
+            format!("{}{}{}", indent, marker, text)
+        }
+    }).collect()
+}
+
+// wraps `text` in a block comment or removes it. also returns the change of the number
+// of chars in front of the content, to keep the cursor on the same char of the content.
+pub fn toggle_block_comment(text: &str, start: &str, end: &str) -> (String, isize) {
+    let inner = text.strip_prefix(start.trim()).and_then(|t| t.strip_suffix(end.trim()));
+    match inner {
+        Some(inner) => {
+            let mut removed = start.trim().len();
+            let inner = match inner.strip_prefix(' ') {
+                Some(rest) if start.ends_with(' ') => {
+                    removed += 1;
+                    rest
+                }
+                _ => inner,
+            };
+            let inner = if end.starts_with(' ') { inner.strip_suffix(' ').unwrap_or(inner) } else { inner };
+            (inner.to_string(), -(text[..removed].chars().count() as isize))
+        }
+        None => (format!("{}{}{}", start, text, end), start.chars().count() as isize),
+    }
+}
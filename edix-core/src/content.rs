@@ -0,0 +1,388 @@
+use crate::highlight::{highlight_line, initial_state, LineState};
+use crate::font_rules::FontRules;
+use crate::fonts::FontProfile;
+use crate::fonts::{Font, DEFAULT_ROLE};
+use crate::indent::IndentUnit;
+use crate::measure::Measure;
+use ropey::Rope;
+use std::cell::RefCell;
+use std::rc::Rc;
+use syntect::highlighting::FontStyle;
+
+pub trait TextBackend {
+    fn num_lines(&self) -> usize;
+
+    fn num_chars_of_line(&self, y: usize) -> usize;
+
+    // width of chars `0..x_max`
+    // x_max is in range 0..=line_len
+    fn width_of_line(&self, y: usize, x_max: usize) -> usize;
+
+    // widths of the prefixes `0..x` of line `y` for all x in 0..=line_len, in ascending order
+    fn widths_of_line(&self, y: usize) -> Rc<[usize]>;
+
+    // height of each line in px
+    fn line_height(&self) -> usize;
+
+    // char index of the first char of line `y`
+    fn line_to_char(&self, y: usize) -> usize;
+
+    // line containing the char index `idx` (idx is in range 0..=num_chars)
+    fn char_to_line(&self, idx: usize) -> usize;
+
+    // chars of line `y` without the trailing '\n'
+    fn line_chars(&self, y: usize) -> Vec<char>;
+
+    // indices in line `y` where highlighting tokens start, in ascending order
+    fn token_starts(&self, y: usize) -> Vec<usize>;
+}
+
+pub struct Content {
+    raw: Rope,
+    rich: Vec<Rc<Vec<Region>>>,
+    // version of each line's regions, a line's regions only changed if its version did.
+    // versions are never reused, so they also identify lines across edits.
+    versions: Vec<u64>,
+    next_version: u64,
+    // incremented on every change of the text
+    text_version: u64,
+    // highlighting state at the start of each line, used to re-highlight incrementally
+    states: Vec<LineState>,
+    // widths of all prefixes of each line, measured lazily and reset when a line changes
+    line_widths: RefCell<Vec<Option<Rc<[usize]>>>>,
+    measure: Box<dyn Measure>,
+    pub options: RichContentOptions,
+}
+
+impl Content {
+    pub fn from_str(s: &str, options: RichContentOptions, measure: Box<dyn Measure>) -> Self {
+        let mut c = Self {
+            raw: Rope::from_str(s),
+            rich: vec!(),
+            versions: vec!(),
+            next_version: 0,
+            text_version: 0,
+            states: vec!(),
+            line_widths: RefCell::new(vec!()),
+            measure,
+            options,
+        };
+        c.update_rich();
+        c
+    }
+
+    pub fn update(&mut self, from: usize, to: usize, s: &str) {
+        self.text_version += 1;
+        let first_line = self.raw.char_to_line(from);
+        let old_last_line = self.raw.char_to_line(to);
+        self.raw.remove(from..to);
+        self.raw.insert(from, s);
+        let new_last_line = self.raw.char_to_line(from + s.chars().count());
+
+        // placeholders for the changed lines, they're filled in by `rehighlight`
+        let num_new = new_last_line - first_line;
+        let state = self.states[first_line].clone();
+        self.rich.splice(first_line+1..=old_last_line, (0..num_new).map(|_| Rc::new(vec!())));
+        let versions: Vec<u64> = (0..num_new).map(|_| self.new_version()).collect();
+        self.versions.splice(first_line+1..=old_last_line, versions);
+        self.states.splice(first_line+1..=old_last_line, (0..num_new).map(|_| state.clone()));
+        self.line_widths.get_mut().splice(first_line+1..=old_last_line, (0..num_new).map(|_| None));
+        self.rehighlight(first_line, new_last_line);
+    }
+
+    pub fn get_raw_text(&self) -> String {
+        self.raw.to_string()
+    }
+
+    pub fn get_text(&self, from: usize, to: usize) -> String {
+        self.raw.slice(from..to).to_string()
+    }
+
+    pub fn char_at(&self, idx: usize) -> char {
+        self.raw.char(idx)
+    }
+
+    pub fn num_chars(&self) -> usize {
+        self.raw.len_chars()
+    }
+
+    pub fn get_lines(&self) -> &[Rc<Vec<Region>>] {
+        &self.rich
+    }
+
+    // changes whenever the regions of line `y` change, and is unique among all lines
+    pub fn line_version(&self, y: usize) -> u64 {
+        self.versions[y]
+    }
+
+    // changes whenever the text changes, e.g. to know when search results are outdated
+    pub fn text_version(&self) -> u64 {
+        self.text_version
+    }
+
+    fn new_version(&mut self) -> u64 {
+        self.next_version += 1;
+        self.next_version
+    }
+
+    fn update_rich(&mut self) {
+        /*
+        if self.raw.is_empty() {
+            self.rich = vec!(vec!());
+            return;
+        }
+        let mut regions = vec!();
+        for line in self.raw.lines() {
+            let mut line_regions = vec!();
+            let mut monospace = false;
+            for word in line.split(' ') {
+                let sty = format!("font-family: {};", if monospace { "mono" } else { "prop" });
+                line_regions.push(Region::new(word.to_string(), sty.to_string()));
+                line_regions.push(Region::new(" ".to_string(), sty.to_string()));
+                monospace = !monospace;
+            }
+            line_regions.pop(); // pop last space
+            regions.push(line_regions);
+        }
+        self.rich = regions;
+        */
+        let num_lines = self.raw.len_lines();
+        self.rich = (0..num_lines).map(|_| Rc::new(vec!())).collect();
+        self.versions = (0..num_lines).map(|_| self.new_version()).collect();
+        self.states = vec!(initial_state(&self.options); num_lines);
+        self.line_widths = RefCell::new(vec!(None; num_lines));
+        self.rehighlight(0, num_lines - 1);
+    }
+
+    // highlights the lines `first..=last`, then continues with the following lines
+    // until the parser state matches the cached state of the next line again
+    fn rehighlight(&mut self, first: usize, last: usize) {
+        let mut state = self.states[first].clone();
+        let mut y = first;
+        loop {
+            let regions = highlight_line(&self.raw.line(y).to_string(), &mut state, &self.options);
+            // lines after the edit are often highlighted the same as before
+            if *self.rich[y] != regions {
+                self.rich[y] = Rc::new(regions);
+                self.versions[y] = self.new_version();
+                self.line_widths.get_mut()[y] = None;
+            }
+            y += 1;
+            if y >= self.rich.len() || (y > last && self.states[y] == state) {
+                break;
+            }
+            self.states[y] = state.clone();
+        }
+    }
+
+    pub fn set_font_profile(&mut self, profile: String) {
+        self.options.selected_font_profile = profile;
+        self.update_rich()
+    }
+
+    pub fn set_syntax(&mut self, syntax: String) {
+        self.options.selected_syntax = syntax;
+        self.update_rich()
+    }
+
+    // the indent width is also the tab width, so measured line widths are reset
+    pub fn set_indent_unit(&mut self, indent_unit: IndentUnit) {
+        self.options.indent_unit = indent_unit;
+        self.line_widths = RefCell::new(vec!(None; self.rich.len()));
+    }
+
+    pub fn set_theme(&mut self, theme: String) {
+        self.options.selected_theme = theme;
+        self.update_rich()
+    }
+
+    // distance of tab stops in px, a number of monospace cells independent of the region fonts
+    pub fn tab_width(&self) -> f64 {
+        let size = self.options.font_profile().font_for_role(DEFAULT_ROLE).map_or(Font::default().size, |f| f.size);
+        let cell = Font { size, ..Font::default() };
+        let width = self.measure.width(&cell, FontStyle::empty(), "\u{00a0}") * self.options.indent_unit.width as f64;
+        if width > 0.0 { width } else { 1.0 }
+    }
+
+    // widths of the prefixes `0..x` of line `y` for all x in 0..=line_len
+    fn measure_line(&self, y: usize) -> Vec<usize> {
+        let tab_width = self.tab_width();
+        let mut widths = vec!(0);
+        let mut offset = 0.0;
+        for region in self.rich[y].iter() {
+            let letter_spacing = region.font.letter_spacing as f64;
+            // text since the last tab, its prefixes are measured as a whole to account for kerning
+            let mut run = String::new();
+            let mut run_len = 0;
+            let mut start = offset;
+            for c in region.s.chars() {
+                if c == '\t' {
+                    offset = ((offset / tab_width).floor() + 1.0) * tab_width;
+                    run.clear();
+                    run_len = 0;
+                    start = offset;
+                } else {
+                    run.push(if c == ' ' { '\u{00a0}' } else { c });
+                    run_len += 1;
+                    let width = self.measure.width(&region.font, region.font_style, &run);
+                    offset = start + width + letter_spacing * run_len as f64;
+                }
+                widths.push(offset.round() as usize);
+            }
+        }
+        widths
+    }
+}
+
+#[derive(Clone)]
+pub struct RichContentOptions {
+    pub selected_font_profile: String,
+    pub font_profiles: Vec<FontProfile>,
+    pub selected_syntax: String,
+    pub selected_theme: String,
+    pub font_rules: FontRules,
+    pub indent_unit: IndentUnit,
+    pub line_height: usize,
+}
+
+impl RichContentOptions {
+    // the selected font profile, or the first one if it doesn't exist
+    pub fn font_profile(&self) -> &FontProfile {
+        self.font_profiles.iter()
+            .find(|p| p.name == self.selected_font_profile)
+            .unwrap_or(&self.font_profiles[0])
+    }
+}
+
+impl TextBackend for Content {
+
+    fn num_lines(&self) -> usize {
+        self.raw.len_lines()
+    }
+
+    fn num_chars_of_line(&self, y: usize) -> usize {
+        // every line but the last one ends with a '\n'
+        let len = self.raw.line(y).len_chars();
+        if y + 1 < self.raw.len_lines() { len - 1 } else { len }
+    }
+
+    fn width_of_line(&self, y: usize, x_max: usize) -> usize {
+        let widths = self.widths_of_line(y);
+        widths[x_max.min(widths.len() - 1)]
+    }
+
+    fn widths_of_line(&self, y: usize) -> Rc<[usize]> {
+        if let Some(widths) = &self.line_widths.borrow()[y] {
+            return widths.clone();
+        }
+        let widths: Rc<[usize]> = self.measure_line(y).into();
+        if self.measure.is_ready() {
+            self.line_widths.borrow_mut()[y] = Some(widths.clone());
+        }
+        widths
+    }
+
+    fn line_height(&self) -> usize {
+        self.options.line_height
+    }
+
+    fn line_to_char(&self, y: usize) -> usize {
+        self.raw.line_to_char(y)
+    }
+
+    fn char_to_line(&self, idx: usize) -> usize {
+        self.raw.char_to_line(idx)
+    }
+
+    fn line_chars(&self, y: usize) -> Vec<char> {
+        self.raw.line(y).chars().take(self.num_chars_of_line(y)).collect()
+    }
+
+    fn token_starts(&self, y: usize) -> Vec<usize> {
+        self.rich[y].iter().scan(0, |start, r| {
+            let token_start = *start;
+            *start += r.s.chars().count();
+            Some(token_start)
+        }).collect()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Region {
+    s: String,
+    sty: String,
+    font: Font,
+    font_style: FontStyle,
+    title: String
+}
+
+impl Region {
+    pub fn new(s: String, sty: String, font: Font, font_style: FontStyle, title: String) -> Region {
+        Region { s, sty, font, font_style, title }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.s
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    // full inline style of the region, including the theme's bold / italic / underline
+    pub fn css(&self) -> String {
+        let mut css = format!("{}{}", self.sty, self.font.to_css());
+        if self.font_style.contains(FontStyle::BOLD) {
+            css.push_str("font-weight: bold;");
+        }
+        if self.font_style.contains(FontStyle::ITALIC) {
+            css.push_str("font-style: italic;");
+        }
+        if self.font_style.contains(FontStyle::UNDERLINE) {
+            css.push_str("text-decoration: underline;");
+        }
+        css
+    }
+}
+
+// splits the regions of a line at the boundaries of the selections `sels` (line-relative
+// char indices) and marks each resulting region as selected or not
+pub fn split_regions(regions: &[Region], sels: &[(usize, usize)]) -> Vec<(Region, bool)> {
+    let mut res = vec!();
+    let mut start = 0;
+    for r in regions {
+        let len = r.s.chars().count();
+        let end = start + len;
+        let mut cuts: Vec<usize> = sels.iter()
+            .flat_map(|&(from, to)| vec!(from, to))
+            .filter(|&x| x > start && x < end)
+            .map(|x| x - start)
+            .chain(vec!(0, len))
+            .collect();
+        cuts.sort_unstable();
+        cuts.dedup();
+        for w in cuts.windows(2) {
+            let (from, to) = (w[0], w[1]);
+            let selected = sels.iter().any(|&(a, b)| a <= start + from && start + to <= b);
+            let s = r.s.chars().skip(from).take(to - from).collect();
+            res.push((Region::new(s, r.sty.clone(), r.font.clone(), r.font_style, r.title.clone()), selected));
+        }
+        start = end;
+    }
+    res
+}
+
+// content with DOM-free measurement, each char being 9px wide unless `measure` says otherwise
+#[cfg(test)]
+pub(crate) fn test_content<M: Measure + 'static>(s: &str, measure: M) -> Content {
+    let options = RichContentOptions {
+        selected_font_profile: "Monospace".to_string(),
+        font_profiles: crate::fonts::default_font_profiles(),
+        selected_syntax: "Plain Text".to_string(),
+        selected_theme: "InspiredGitHub".to_string(),
+        font_rules: FontRules::default(),
+        indent_unit: IndentUnit::default(),
+        line_height: 20,
+    };
+    Content::from_str(s, options, Box::new(measure))
+}
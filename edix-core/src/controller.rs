@@ -0,0 +1,1159 @@
+
+use crate::cursor::{
+    Cursor,
+};
+use crate::history::{
+    Change,
+    History,
+};
+
+use crate::content::{
+    Content,
+    RichContentOptions,
+    TextBackend,
+};
+use crate::comment::{comment_markers, toggle_block_comment, toggle_line_comments, CommentMarkers};
+use crate::indent::{self, IndentUnit};
+use crate::keymap::Command;
+use crate::search::{Search, SearchOptions};
+use crate::words::{class, CharClass};
+use crate::measure::Measure;
+
+/*
+
+Cursor
+Content
+  RawText
+  Text
+
+RawText -> Text (syntax highlighting)
+
+Events:
+Cursor movement
+Copy/Paste/Cut
+Char
+Bs/Del
+Enter
+
+State Updates:
+update_raw_text(t) (updates raw_text and text)
+
+
+*/
+
+
+pub struct Controller {
+    // the primary cursor, most operations only consider this one
+    cursor: Cursor,
+    // additional carets, edits like typing are applied at all of them
+    others: Vec<Cursor>,
+    content: Content,
+    history: History,
+    // the box selection the cursors were created from, if they still are
+    boxed: Option<BoxSelection>,
+}
+
+// a rectangular selection between the pixel x positions of its corners, the text of each line
+// in between is selected from its char boundary closest to one x to the one closest to the other
+#[derive(Clone, Copy)]
+struct BoxSelection {
+    anchor: (usize, usize),  // (line, x_px)
+    head: (usize, usize),  // (line, x_px)
+}
+
+impl Controller {
+    pub fn new(txt: &str, options: RichContentOptions, measure: Box<dyn Measure>) -> Self {
+        Self::from_content(Content::from_str(txt, options, measure))
+    }
+
+    pub fn from_content(content: Content) -> Self {
+        Self {
+            cursor: Cursor::new(),
+            others: vec!(),
+            content,
+            history: History::new(),
+            boxed: None,
+        }
+    }
+
+    pub fn get_x(&self) -> usize {
+        self.cursor.get_x(&self.content)
+    }
+    
+    pub fn get_y(&self) -> usize {
+        self.cursor.get_y(&self.content)
+    }
+
+    pub fn get_content(&self) -> &Content {
+        &self.content
+    }
+
+    pub fn get_raw_text(&self) -> String {
+        self.content.get_raw_text()
+    }
+
+    pub fn execute(&mut self, command: Command) {
+        match command {
+            Command::CursorUp => self.cursor_up(false),
+            Command::CursorDown => self.cursor_down(false),
+            Command::CursorLeft => self.cursor_left(false),
+            Command::CursorRight => self.cursor_right(false),
+            Command::CursorWordLeft => self.cursor_word_left(false),
+            Command::CursorWordRight => self.cursor_word_right(false),
+            Command::CursorHome => self.cursor_home(false),
+            Command::CursorEnd => self.cursor_end(false),
+            Command::SelectUp => self.cursor_up(true),
+            Command::SelectDown => self.cursor_down(true),
+            Command::SelectLeft => self.cursor_left(true),
+            Command::SelectRight => self.cursor_right(true),
+            Command::SelectWordLeft => self.cursor_word_left(true),
+            Command::SelectWordRight => self.cursor_word_right(true),
+            Command::SelectHome => self.cursor_home(true),
+            Command::SelectEnd => self.cursor_end(true),
+            Command::Backspace => self.key_backspace(),
+            Command::Delete => self.key_delete(),
+            Command::WordBackspace => self.key_word_backspace(),
+            Command::WordDelete => self.key_word_delete(),
+            Command::Enter => self.key_enter(),
+            Command::Indent => self.indent(),
+            Command::Dedent => self.dedent(),
+            Command::ToggleComment => self.toggle_comment(),
+            Command::AddNextOccurrence => self.add_next_occurrence(),
+            Command::CollapseCursors => self.collapse_cursors(),
+            Command::BoxSelectUp => self.box_select_up(),
+            Command::BoxSelectDown => self.box_select_down(),
+            Command::BoxSelectLeft => self.box_select_left(),
+            Command::BoxSelectRight => self.box_select_right(),
+            Command::Find => {}
+            Command::Undo => self.undo(),
+            Command::Redo => self.redo(),
+        }
+    }
+
+    pub fn get_selection(&self) -> Option<(usize, usize)> {
+        self.cursor.get_selection(&self.content)
+    }
+
+    // selections of all cursors, ordered by position
+    pub fn get_selections(&self) -> Vec<(usize, usize)> {
+        let mut selections: Vec<(usize, usize)> = std::iter::once(&self.cursor).chain(self.others.iter())
+            .filter_map(|c| c.get_selection(&self.content))
+            .collect();
+        selections.sort_unstable();
+        selections
+    }
+
+    // pixel positions (x, y) of all carets
+    pub fn get_carets(&self) -> Vec<(usize, usize)> {
+        std::iter::once(&self.cursor).chain(self.others.iter())
+            .map(|c| (c.get_x(&self.content), c.get_y(&self.content)))
+            .collect()
+    }
+
+    // char index of the cursor
+    pub fn get_idx(&self) -> usize {
+        self.cursor.get_idx(&self.content)
+    }
+
+    pub fn set_idx(&mut self, idx: usize, select: bool) {
+        self.begin_move(select);
+        self.cursor.set_idx(&self.content, idx)
+    }
+
+    pub fn cursor_up(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, _| c.cursor_up(content))
+    }
+
+    pub fn cursor_down(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, _| c.cursor_down(content))
+    }
+
+    pub fn cursor_left(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, selection| match selection {
+            Some((from, _)) if !select => c.set_idx(content, from),
+            _ => c.cursor_left(content),
+        })
+    }
+
+    pub fn cursor_right(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, selection| match selection {
+            Some((_, to)) if !select => c.set_idx(content, to),
+            _ => c.cursor_right(content),
+        })
+    }
+
+    pub fn cursor_word_left(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, _| c.cursor_word_left(content))
+    }
+
+    pub fn cursor_word_right(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, _| c.cursor_word_right(content))
+    }
+
+    pub fn cursor_home(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, _| c.cursor_home(content))
+    }
+
+    pub fn cursor_end(&mut self, select: bool) {
+        self.move_cursors(select, |c, content, _| c.cursor_end(content))
+    }
+
+    pub fn mouse_click(&mut self, x: usize, y: usize) {
+        self.begin_move(false);
+        self.cursor.mouse_click(&self.content, x, y)
+    }
+
+    // moves the cursor to the clicked position, extending the selection (Shift+click, dragging)
+    pub fn mouse_select(&mut self, x: usize, y: usize) {
+        self.begin_move(true);
+        self.cursor.mouse_click(&self.content, x, y)
+    }
+
+    // selects the clicked word, or the run of whitespace or punctuation
+    pub fn mouse_double_click(&mut self, x: usize, y: usize) {
+        self.mouse_click(x, y);
+        let (from, to) = self.cursor.word_range(&self.content);
+        self.select(from, to);
+    }
+
+    // selects the clicked line
+    pub fn mouse_triple_click(&mut self, x: usize, y: usize) {
+        self.mouse_click(x, y);
+        let (from, to) = self.cursor.line_range(&self.content);
+        self.select(from, to);
+    }
+
+    // adds a caret at the clicked position, which becomes the primary cursor
+    pub fn add_cursor(&mut self, x: usize, y: usize) {
+        self.history.seal();
+        self.boxed = None;
+        let mut cursor = Cursor::new();
+        cursor.mouse_click(&self.content, x, y);
+        self.others.push(std::mem::replace(&mut self.cursor, cursor));
+        self.merge_cursors();
+    }
+
+    // selects the word at the cursor, or adds a cursor selecting the next occurrence of the
+    // selected text
+    pub fn add_next_occurrence(&mut self) {
+        self.history.seal();
+        self.boxed = None;
+        let (from, to) = match self.get_selection() {
+            Some(selection) => selection,
+            None => {
+                let idx = self.cursor.get_idx(&self.content);
+                if let Some((from, to)) = self.word_at(idx) {
+                    self.select(from, to);
+                }
+                return;
+            }
+        };
+        let search = Search::new(&self.content.get_text(from, to), SearchOptions { regex: false, case_sensitive: true });
+        let matches = match search {
+            Ok(search) => self.find_matches(&search),
+            Err(_) => return,
+        };
+        let selections = self.get_selections();
+        let next = matches.iter().filter(|m| m.0 >= to).chain(matches.iter())
+            .find(|m| !selections.contains(*m));
+        if let Some(&(from, to)) = next {
+            let mut cursor = Cursor::new();
+            cursor.set_idx(&self.content, from);
+            cursor.set_anchor();
+            cursor.set_idx(&self.content, to);
+            self.others.push(std::mem::replace(&mut self.cursor, cursor));
+        }
+    }
+
+    // removes all but the primary cursor, or its selection if there's only one cursor
+    pub fn collapse_cursors(&mut self) {
+        self.boxed = None;
+        if self.others.is_empty() {
+            self.cursor.clear_anchor();
+        }
+        self.others.clear();
+    }
+
+    // selects the box between the pixel positions (x, y) `anchor` and `head`, e.g. while dragging
+    pub fn box_select(&mut self, anchor: (usize, usize), head: (usize, usize)) {
+        let line = |y: usize| (y / self.content.line_height()).min(self.content.num_lines() - 1);
+        let b = BoxSelection { anchor: (line(anchor.1), anchor.0), head: (line(head.1), head.0) };
+        self.set_box(b);
+    }
+
+    pub fn box_select_up(&mut self) {
+        let mut b = self.current_box();
+        b.head.0 = b.head.0.saturating_sub(1);
+        self.set_box(b);
+    }
+
+    pub fn box_select_down(&mut self) {
+        let mut b = self.current_box();
+        b.head.0 = (b.head.0 + 1).min(self.content.num_lines() - 1);
+        self.set_box(b);
+    }
+
+    pub fn box_select_left(&mut self) {
+        let mut b = self.current_box();
+        let x = self.box_x_idx(b.head);
+        b.head.1 = self.content.width_of_line(b.head.0, x.saturating_sub(1));
+        self.set_box(b);
+    }
+
+    pub fn box_select_right(&mut self) {
+        let mut b = self.current_box();
+        let x = (self.box_x_idx(b.head) + 1).min(self.content.num_chars_of_line(b.head.0));
+        b.head.1 = self.content.width_of_line(b.head.0, x);
+        self.set_box(b);
+    }
+
+    // the box selection to extend, an empty one at the cursor if there's none
+    fn current_box(&self) -> BoxSelection {
+        self.boxed.unwrap_or_else(|| {
+            let pos = (self.cursor.get_y(&self.content) / self.content.line_height(), self.cursor.get_x(&self.content));
+            BoxSelection { anchor: pos, head: pos }
+        })
+    }
+
+    // char index within its line closest to the box corner `(line, x_px)`
+    fn box_x_idx(&self, (y, x): (usize, usize)) -> usize {
+        let mut cursor = Cursor::new();
+        cursor.mouse_click(&self.content, x, y * self.content.line_height());
+        cursor.get_idx(&self.content) - self.content.line_to_char(y)
+    }
+
+    // replaces the cursors with one per line of the box, the one on the head's line is primary
+    fn set_box(&mut self, b: BoxSelection) {
+        self.history.seal();
+        let line_height = self.content.line_height();
+        let (first, last) = (b.anchor.0.min(b.head.0), b.anchor.0.max(b.head.0));
+        let mut cursors: Vec<Cursor> = (first..=last).map(|y| {
+            let mut cursor = Cursor::new();
+            cursor.mouse_click(&self.content, b.anchor.1, y * line_height);
+            cursor.set_anchor();
+            cursor.mouse_click(&self.content, b.head.1, y * line_height);
+            cursor
+        }).collect();
+        self.cursor = if b.head.0 < b.anchor.0 { cursors.remove(0) } else { cursors.pop().unwrap() };
+        self.others = cursors;
+        self.boxed = Some(b);
+    }
+
+    // char index range of the word around `idx`
+    fn word_at(&self, idx: usize) -> Option<(usize, usize)> {
+        let y = self.content.char_to_line(idx);
+        let line_start = self.content.line_to_char(y);
+        let chars = self.content.line_chars(y);
+        let is_word = |x: usize| chars.get(x).map(|&c| class(c) == CharClass::Word) == Some(true);
+        let x = idx - line_start;
+        let mut from = x;
+        while from > 0 && is_word(from - 1) {
+            from -= 1;
+        }
+        let mut to = x;
+        while is_word(to) {
+            to += 1;
+        }
+        if from < to { Some((line_start + from, line_start + to)) } else { None }
+    }
+
+    // breaks the line, indenting the new line according to the syntax's indentation rules
+    pub fn key_enter(&mut self) {
+        self.edit_at_cursors(false, |this, c| {
+            let idx = c.get_idx(&this.content);
+            let (from, to) = c.get_selection(&this.content).unwrap_or((idx, idx));
+            let y = this.content.char_to_line(from);
+            let to_y = this.content.char_to_line(to);
+            let before = this.content.get_text(this.content.line_to_char(y), from);
+            let after = this.content.get_text(to, this.content.line_to_char(to_y) + this.content.num_chars_of_line(to_y));
+            let prev = if y > 0 { Some(this.line_text(y - 1)) } else { None };
+            let (s, cursor) = indent::line_break(prev.as_deref(), &before, &after, &this.content.options);
+            Some((from, to, s, cursor))
+        });
+    }
+
+    pub fn key_backspace(&mut self) {
+        self.delete_at_cursors(|this, c| {
+            let idx = c.get_idx(&this.content);
+            let line_start = this.content.line_to_char(this.content.char_to_line(idx));
+            let before = this.content.get_text(line_start, idx);
+            if !before.is_empty() && before.trim_start().is_empty() {
+                // in leading whitespace, remove one indentation level
+                Some(line_start + this.content.options.indent_unit.dedent_to_stop(&before).chars().count())
+            } else {
+                idx.checked_sub(1)
+            }
+        });
+    }
+
+    pub fn key_delete(&mut self) {
+        self.delete_at_cursors(|this, c| {
+            Some(c.get_idx(&this.content) + 1).filter(|&to| to <= this.content.num_chars())
+        });
+    }
+
+    pub fn key_word_backspace(&mut self) {
+        self.delete_at_cursors(|this, c| {
+            let mut target = c.clone();
+            target.cursor_word_left(&this.content);
+            Some(target.get_idx(&this.content))
+        });
+    }
+
+    pub fn key_word_delete(&mut self) {
+        self.delete_at_cursors(|this, c| {
+            let mut target = c.clone();
+            target.cursor_word_right(&this.content);
+            Some(target.get_idx(&this.content))
+        });
+    }
+
+    // deletes the selection of each cursor, or the text between it and the index returned by `f`
+    fn delete_at_cursors<F: Fn(&Self, &Cursor) -> Option<usize>>(&mut self, f: F) {
+        self.edit_at_cursors(false, |this, c| {
+            if let Some((from, to)) = c.get_selection(&this.content) {
+                return Some((from, to, String::new(), 0));
+            }
+            let idx = c.get_idx(&this.content);
+            let other = f(this, c)?;
+            Some((idx.min(other), idx.max(other), String::new(), 0))
+        });
+    }
+
+    pub fn key_char(&mut self, c: char) {
+        let idx = self.cursor.get_idx(&self.content);
+        let from = self.get_selection().map_or(idx, |(from, _)| from);
+        let old_line = self.line_text(self.content.char_to_line(from));
+        self.replace_selection(&c.to_string(), c != '\n');
+        if c != '\n' && self.others.is_empty() {
+            self.electric_dedent(&old_line);
+        }
+    }
+
+    // dedents the cursor line if the typed char made it e.g. a closing bracket line
+    fn electric_dedent(&mut self, old_line: &str) {
+        let idx = self.cursor.get_idx(&self.content);
+        let y = self.content.char_to_line(idx);
+        let prev_y = match (0..y).rev().find(|&y| !self.line_text(y).trim().is_empty()) {
+            Some(prev_y) => prev_y,
+            None => return,
+        };
+        let prev_prev = if prev_y > 0 { Some(self.line_text(prev_y - 1)) } else { None };
+        let line = self.line_text(y);
+        let new_indent = indent::electric_indent(prev_prev.as_deref(), &self.line_text(prev_y), old_line, &line, &self.content.options);
+        if let Some(new_indent) = new_indent {
+            let start = self.content.line_to_char(y);
+            let old_len = line.chars().count() - line.trim_start().chars().count();
+            let new_len = new_indent.chars().count();
+            self.edit(start, start + old_len, &new_indent, idx + new_len - old_len, false);
+        }
+    }
+
+    // indents the selected lines, or inserts whitespace up to the next indentation stop
+    pub fn indent(&mut self) {
+        if let Some((first, last)) = self.selected_lines() {
+            let unit = self.content.options.indent_unit.text();
+            self.change_lines(first, last, |lines| lines.iter().map(|line| {
+                if line.is_empty() { line.to_string() } else { format!("{}{}", unit, line) }
+            }).collect());
+        } else {
+            let idx = self.cursor.get_idx(&self.content);
+            let line_start = self.content.line_to_char(self.content.char_to_line(idx));
+            let col = self.content.options.indent_unit.columns(&self.content.get_text(line_start, idx));
+            self.insert(&self.content.options.indent_unit.text_from(col));
+        }
+    }
+
+    // removes one indentation level from the selected lines or the cursor line
+    pub fn dedent(&mut self) {
+        let (first, last) = self.selected_or_cursor_lines();
+        let unit = self.content.options.indent_unit;
+        self.change_lines(first, last, |lines| lines.iter().map(|line| {
+            let indent = indent::leading_whitespace(line);
+            format!("{}{}", unit.dedent_to_stop(indent), &line[indent.len()..])
+        }).collect());
+    }
+
+    // comments or uncomments the selected lines or the cursor line, using line comments if
+    // the syntax has them and a block comment around the selection (or line) otherwise
+    pub fn toggle_comment(&mut self) {
+        match comment_markers(&self.content.options) {
+            Some(CommentMarkers::Line(marker)) => {
+                let (first, last) = self.selected_or_cursor_lines();
+                self.change_lines(first, last, |lines| toggle_line_comments(lines, &marker));
+            }
+            Some(CommentMarkers::Block(start, end)) => {
+                let idx = self.cursor.get_idx(&self.content);
+                let selection = self.get_selection();
+                let (from, to) = selection.unwrap_or_else(|| {
+                    // the line without its indentation
+                    let y = self.content.char_to_line(idx);
+                    let line = self.line_text(y);
+                    let line_start = self.content.line_to_char(y);
+                    let indent = indent::leading_whitespace(&line).chars().count();
+                    (line_start + indent, line_start + line.chars().count())
+                });
+                let (s, shift) = toggle_block_comment(&self.content.get_text(from, to), &start, &end);
+                let len = s.chars().count();
+                let cursor = ((idx.max(from) as isize + shift).max(from as isize) as usize).min(from + len);
+                self.edit(from, to, &s, cursor, false);
+                if selection.is_some() {
+                    self.cursor.set_idx(&self.content, from);
+                    self.cursor.set_anchor();
+                    self.cursor.set_idx(&self.content, from + len);
+                }
+            }
+            None => {}
+        }
+    }
+
+    // lines `first..=last` touched by a selection spanning several lines. a selection
+    // ending at the start of a line doesn't include that line.
+    fn selected_lines(&self) -> Option<(usize, usize)> {
+        let (from, to) = self.get_selection()?;
+        let first = self.content.char_to_line(from);
+        let mut last = self.content.char_to_line(to);
+        if last > first && self.content.line_to_char(last) == to {
+            last -= 1;
+        }
+        if first == last && self.content.char_to_line(to) == last {
+            return None;
+        }
+        Some((first, last))
+    }
+
+    fn selected_or_cursor_lines(&self) -> (usize, usize) {
+        self.selected_lines().unwrap_or_else(|| {
+            let y = self.content.char_to_line(self.cursor.get_idx(&self.content));
+            (y, y)
+        })
+    }
+
+    // replaces the lines `first..=last` by `f(lines)` in a single edit. a selection is extended
+    // to the whole lines, otherwise the cursor keeps its distance to the end of its line.
+    fn change_lines<F: Fn(&[String]) -> Vec<String>>(&mut self, first: usize, last: usize, f: F) {
+        let had_selection = self.get_selection().is_some();
+        let idx = self.cursor.get_idx(&self.content);
+        let y = self.content.char_to_line(idx);
+        let to_line_end = self.content.line_to_char(y) + self.content.num_chars_of_line(y) - idx;
+
+        let from = self.content.line_to_char(first);
+        let to = self.content.line_to_char(last) + self.content.num_chars_of_line(last);
+        let lines: Vec<String> = (first..=last).map(|y| self.line_text(y)).collect();
+        let s = f(&lines).join("\n");
+        self.edit(from, to, &s, from, false);
+        if had_selection {
+            self.cursor.set_anchor();
+            self.cursor.set_idx(&self.content, from + s.chars().count());
+        } else {
+            let line_start = self.content.line_to_char(y);
+            let line_end = line_start + self.content.num_chars_of_line(y);
+            self.collapse_selection(line_end.saturating_sub(to_line_end).max(line_start));
+        }
+    }
+
+    pub fn set_indent_unit(&mut self, indent_unit: IndentUnit) {
+        self.content.set_indent_unit(indent_unit);
+    }
+
+    fn line_text(&self, y: usize) -> String {
+        self.content.line_chars(y).into_iter().collect()
+    }
+
+    // text of all selections, one per line
+    pub fn get_selected_text(&self) -> Option<String> {
+        let selections = self.get_selections();
+        if selections.is_empty() {
+            return None;
+        }
+        let texts: Vec<String> = selections.iter().map(|&(from, to)| self.content.get_text(from, to)).collect();
+        Some(texts.join("\n"))
+    }
+
+    pub fn cut(&mut self) -> Option<String> {
+        let text = self.get_selected_text();
+        if text.is_some() {
+            self.insert("");
+        }
+        text
+    }
+
+    pub fn paste(&mut self, s: &str) {
+        self.insert(&s.replace("\r\n", "\n").replace('\r', "\n"))
+    }
+
+    // replaces the selection (or inserts at the cursor) and places the cursor after `s`
+    pub fn insert(&mut self, s: &str) {
+        self.replace_selection(s, false)
+    }
+
+    // replaces the chars `from..to` with `s` and puts the cursor after the inserted text
+    pub fn replace(&mut self, from: usize, to: usize, s: &str) {
+        self.edit(from, to, s, from + s.chars().count(), false);
+    }
+
+    // char index ranges of all matches of `search`
+    pub fn find_matches(&self, search: &Search) -> Vec<(usize, usize)> {
+        search.find_all(&self.content.get_raw_text())
+    }
+
+    // selects the next (or previous) match after (or before) the cursor, wrapping around at the
+    // end of the text. returns false if there's no match.
+    pub fn find_next(&mut self, search: &Search, backwards: bool) -> bool {
+        let matches = self.find_matches(search);
+        let idx = self.cursor.get_idx(&self.content);
+        let (from, to) = self.get_selection().unwrap_or((idx, idx));
+        let next = if backwards {
+            matches.iter().rev().find(|m| m.0 < from).or_else(|| matches.last())
+        } else {
+            matches.iter().find(|m| m.0 >= to).or_else(|| matches.first())
+        };
+        match next {
+            Some(&(from, to)) => {
+                self.select(from, to);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // replaces the selected match and selects the next one, or just selects the next match
+    // if no match is selected
+    pub fn replace_next(&mut self, search: &Search, replacement: &str) {
+        let selection = self.get_selection();
+        let replacements = search.replacements(&self.content.get_raw_text(), replacement);
+        let selected = replacements.into_iter().find(|(from, to, _)| Some((*from, *to)) == selection);
+        if let Some((from, to, s)) = selected {
+            self.edit(from, to, &s, from + s.chars().count(), false);
+        }
+        self.find_next(search, false);
+    }
+
+    // replaces all matches in a single edit, returns the number of replaced matches
+    pub fn replace_all(&mut self, search: &Search, replacement: &str) -> usize {
+        let text = self.content.get_raw_text();
+        let replacements = search.replacements(&text, replacement);
+        let (first, last) = match (replacements.first(), replacements.last()) {
+            (Some(first), Some(last)) => (first.0, last.1),
+            _ => return 0,
+        };
+        let mut s = String::new();
+        let mut pos = first;
+        for (from, to, replaced) in replacements.iter() {
+            s.push_str(&self.content.get_text(pos, *from));
+            s.push_str(replaced);
+            pos = *to;
+        }
+        self.edit(first, last, &s, first + s.chars().count(), false);
+        replacements.len()
+    }
+
+    fn select(&mut self, from: usize, to: usize) {
+        self.begin_move(false);
+        self.cursor.set_idx(&self.content, from);
+        self.cursor.set_anchor();
+        self.cursor.set_idx(&self.content, to);
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(change) = self.history.undo() {
+            self.content.update(change.from, change.inserted_end(), &change.removed);
+            self.collapse_selection(change.cursor_before);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(change) = self.history.redo() {
+            self.content.update(change.from, change.removed_end(), &change.inserted);
+            self.collapse_selection(change.cursor_after);
+        }
+    }
+
+    fn replace_selection(&mut self, s: &str, typing: bool) {
+        self.edit_at_cursors(typing, |this, c| {
+            let idx = c.get_idx(&this.content);
+            let (from, to) = c.get_selection(&this.content).unwrap_or((idx, idx));
+            Some((from, to, s.to_string(), s.chars().count()))
+        });
+    }
+
+    // applies an edit at every cursor as a single edit of the content. `f` returns the range
+    // replaced at a cursor, the replacement and the offset of the cursor within it.
+    // cursors with overlapping ranges are merged.
+    fn edit_at_cursors<F>(&mut self, typing: bool, f: F)
+        where F: Fn(&Self, &Cursor) -> Option<(usize, usize, String, usize)>
+    {
+        let mut edits: Vec<(bool, (usize, usize, String, usize))> = std::iter::once((true, &self.cursor))
+            .chain(self.others.iter().map(|c| (false, c)))
+            .map(|(primary, c)| {
+                let idx = c.get_idx(&self.content);
+                (primary, f(self, c).unwrap_or((idx, idx, String::new(), 0)))
+            })
+            .collect();
+        if edits.iter().all(|(_, (from, to, s, _))| from == to && s.is_empty()) {
+            return;
+        }
+        edits.sort_by_key(|(_, (from, _, _, _))| *from);
+        let mut kept: Vec<(bool, (usize, usize, String, usize))> = vec!();
+        for (primary, edit) in edits {
+            match kept.last_mut() {
+                // overlapping edits become one, keeping the text and caret offset of the first
+                Some(last) if edit.0 < last.1.1 || edit.0 == last.1.0 => {
+                    last.0 |= primary;
+                    last.1.1 = last.1.1.max(edit.1);
+                }
+                _ => kept.push((primary, edit)),
+            }
+        }
+
+        // typing at several carets is grouped like typing at one, as long as nothing is replaced
+        let typing = typing && kept.iter().all(|(_, (from, to, _, _))| from == to);
+        let first = kept[0].1.0;
+        let last = kept[kept.len() - 1].1.1;
+        let mut s = String::new();
+        let mut len = 0;
+        let mut pos = first;
+        let mut carets = vec!();
+        for (primary, (from, to, text, offset)) in kept {
+            let between = self.content.get_text(pos, from);
+            len += between.chars().count();
+            s.push_str(&between);
+            carets.push((primary, first + len + offset));
+            len += text.chars().count();
+            s.push_str(&text);
+            pos = to;
+        }
+        let cursor_after = carets.iter().find(|(primary, _)| *primary).map_or(first, |(_, idx)| *idx);
+        self.edit(first, last, &s, cursor_after, typing);
+        self.others = carets.into_iter().filter(|(primary, _)| !primary).map(|(_, idx)| {
+            let mut cursor = Cursor::new();
+            cursor.set_idx(&self.content, idx);
+            cursor
+        }).collect();
+    }
+
+    // all modifications of the content go through here so that they're recorded in the history.
+    // the cursor is placed at `cursor_after`, other cursors are removed.
+    fn edit(&mut self, from: usize, to: usize, s: &str, cursor_after: usize, typing: bool) {
+        let change = Change {
+            from,
+            removed: self.content.get_text(from, to),
+            inserted: s.to_string(),
+            cursor_before: self.cursor.get_idx(&self.content),
+            cursor_after,
+        };
+        self.content.update(from, to, s);
+        self.history.record(change, typing);
+        self.collapse_selection(cursor_after);
+    }
+
+    // moves all cursors with `f`, which also gets the selection of the cursor before the move
+    fn move_cursors<F>(&mut self, select: bool, f: F)
+        where F: Fn(&mut Cursor, &Content, Option<(usize, usize)>)
+    {
+        self.history.seal();
+        self.boxed = None;
+        for cursor in std::iter::once(&mut self.cursor).chain(self.others.iter_mut()) {
+            let selection = cursor.get_selection(&self.content);
+            if select {
+                cursor.set_anchor();
+            } else {
+                cursor.clear_anchor();
+            }
+            f(cursor, &self.content, selection);
+        }
+        self.merge_cursors();
+    }
+
+    // removes cursors at the same position as another one
+    fn merge_cursors(&mut self) {
+        let mut seen = vec!(self.cursor.get_idx(&self.content));
+        let content = &self.content;
+        self.others.retain(|c| {
+            let idx = c.get_idx(content);
+            let new = !seen.contains(&idx);
+            seen.push(idx);
+            new
+        });
+    }
+
+    // single cursor movement, other cursors are removed
+    fn begin_move(&mut self, select: bool) {
+        self.history.seal();
+        self.others.clear();
+        self.boxed = None;
+        if select {
+            self.cursor.set_anchor();
+        } else {
+            self.cursor.clear_anchor();
+        }
+    }
+
+    // leaves a single caret at `idx`
+    fn collapse_selection(&mut self, idx: usize) {
+        self.others.clear();
+        self.boxed = None;
+        self.cursor.clear_anchor();
+        self.cursor.set_idx(&self.content, idx);
+    }
+
+    pub fn set_font_profile(&mut self, profile: String) {
+        self.content.set_font_profile(profile)
+    }
+
+    pub fn set_syntax(&mut self, syntax: String) {
+        self.content.set_syntax(syntax)
+    }
+
+    pub fn set_theme(&mut self, theme: String) {
+        self.content.set_theme(theme)
+    }
+
+    pub fn get_options(&self) -> &RichContentOptions {
+        &self.content.options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::test_content;
+    use crate::measure::CharWidths;
+
+    fn controller(s: &str) -> Controller {
+        Controller::from_content(test_content(s, CharWidths::monospace(0.6)))
+    }
+
+    fn type_str(c: &mut Controller, s: &str) {
+        for ch in s.chars() {
+            c.key_char(ch);
+        }
+    }
+
+    #[test]
+    fn typing_and_deleting() {
+        let mut c = controller("");
+        type_str(&mut c, "hello");
+        c.key_enter();
+        type_str(&mut c, "world");
+        assert_eq!(c.get_raw_text(), "hello\nworld");
+        assert_eq!((c.get_x(), c.get_y()), (45, 20));
+
+        c.key_backspace();
+        c.cursor_home(false);
+        c.key_backspace();
+        assert_eq!(c.get_raw_text(), "helloworl");
+        c.key_delete();
+        assert_eq!(c.get_raw_text(), "helloorl");
+        assert_eq!((c.get_x(), c.get_y()), (45, 0));
+    }
+
+    #[test]
+    fn only_newlines_end_lines() {
+        // a '\r' is an ordinary char of its line
+        let mut c = controller("a\r\nb");
+        c.cursor_end(false);
+        c.key_char('x');
+        assert_eq!(c.get_raw_text(), "a\rx\nb");
+        c.key_backspace();
+        assert_eq!(c.get_raw_text(), "a\r\nb");
+        assert_eq!(c.get_content().num_lines(), 2);
+    }
+
+    #[test]
+    fn selection_is_replaced() {
+        let mut c = controller("abc\ndef");
+        c.cursor_right(false);
+        c.cursor_down(true);
+        assert_eq!(c.get_selected_text().as_deref(), Some("bc\nd"));
+        c.key_char('X');
+        assert_eq!(c.get_raw_text(), "aXef");
+        assert_eq!(c.get_selection(), None);
+
+        c.cursor_end(true);
+        c.key_backspace();
+        assert_eq!(c.get_raw_text(), "aX");
+    }
+
+    #[test]
+    fn word_movement_and_deletion() {
+        let mut c = controller("foo_bar(baz)\nqux");
+        c.cursor_word_right(false);
+        assert_eq!(c.get_x(), 63);
+        c.cursor_word_right(true);
+        assert_eq!(c.get_selected_text().as_deref(), Some("("));
+        c.cursor_end(false);
+        c.cursor_word_right(false);
+        assert_eq!((c.get_x(), c.get_y()), (0, 20));
+
+        c.key_word_delete();
+        assert_eq!(c.get_raw_text(), "foo_bar(baz)\n");
+        c.key_word_backspace();
+        c.key_word_backspace();
+        assert_eq!(c.get_raw_text(), "foo_bar(baz");
+        c.key_word_backspace();
+        c.key_word_backspace();
+        assert_eq!(c.get_raw_text(), "foo_bar");
+    }
+
+    #[test]
+    fn cut_and_paste() {
+        let mut c = controller("abc");
+        c.cursor_end(true);
+        assert_eq!(c.cut().as_deref(), Some("abc"));
+        assert_eq!(c.cut(), None);
+        c.paste("x\r\ny");
+        c.paste("z");
+        assert_eq!(c.get_raw_text(), "x\nyz");
+    }
+
+    #[test]
+    fn auto_indent() {
+        let mut c = controller("");
+        c.set_syntax("Python".to_string());
+        type_str(&mut c, "  if x:");
+        c.key_enter();
+        type_str(&mut c, "y");
+        c.key_enter();
+        type_str(&mut c, "else:");
+        assert_eq!(c.get_raw_text(), "  if x:\n      y\n  else:");
+
+        let mut c = controller("int f() {}");
+        c.set_syntax("C".to_string());
+        c.cursor_end(false);
+        c.cursor_left(false);
+        c.key_enter();
+        type_str(&mut c, "if (x)");
+        c.key_enter();
+        type_str(&mut c, "y;");
+        c.key_enter();
+        type_str(&mut c, "z;");
+        assert_eq!(c.get_raw_text(), "int f() {\n    if (x)\n        y;\n    z;\n}");
+        c.cursor_end(false);
+        c.key_enter();
+        type_str(&mut c, "}");
+        assert_eq!(c.get_raw_text(), "int f() {\n    if (x)\n        y;\n    z;\n}\n}");
+    }
+
+    #[test]
+    fn indent_and_dedent() {
+        let mut c = controller("a\n\n  b\nc");
+        c.cursor_right(false);
+        c.indent();
+        assert_eq!(c.get_raw_text(), "a   \n\n  b\nc");
+        c.undo();
+        c.cursor_home(false);
+        c.indent();
+        c.key_backspace();
+        assert_eq!(c.get_raw_text(), "a\n\n  b\nc");
+
+        (0..3).for_each(|_| c.cursor_down(true));
+        c.indent();
+        assert_eq!(c.get_raw_text(), "    a\n\n      b\nc");
+        assert_eq!(c.get_selected_text().as_deref(), Some("    a\n\n      b"));
+        c.dedent();
+        c.dedent();
+        assert_eq!(c.get_raw_text(), "a\n\nb\nc");
+        c.undo();
+        assert_eq!(c.get_raw_text(), "a\n\n    b\nc");
+
+        let mut c = controller("      x");
+        c.cursor_end(false);
+        c.cursor_left(false);
+        c.key_backspace();
+        assert_eq!(c.get_raw_text(), "    x");
+        c.dedent();
+        assert_eq!(c.get_raw_text(), "x");
+        assert_eq!(c.get_x(), 0);
+    }
+
+    #[test]
+    fn toggle_comments() {
+        let mut c = controller("def f():\n\n    # x\n    y");
+        c.set_syntax("Python".to_string());
+        (0..4).for_each(|_| c.cursor_down(true));
+        c.toggle_comment();
+        assert_eq!(c.get_raw_text(), "# def f():\n\n#     # x\n#     y");
+        c.toggle_comment();
+        assert_eq!(c.get_raw_text(), "def f():\n\n    # x\n    y");
+
+        c.cursor_up(false);
+        c.toggle_comment();
+        assert_eq!(c.get_raw_text(), "def f():\n\n    x\n    y");
+        c.toggle_comment();
+        assert_eq!(c.get_raw_text(), "def f():\n\n    # x\n    y");
+        assert_eq!(c.get_selection(), None);
+
+        let mut c = controller("  a { b }");
+        c.set_syntax("CSS".to_string());
+        c.cursor_end(false);
+        c.toggle_comment();
+        assert_eq!(c.get_raw_text(), "  /*a { b }*/");
+        assert_eq!(c.get_x(), 9 * 11);
+        c.toggle_comment();
+        assert_eq!(c.get_raw_text(), "  a { b }");
+    }
+
+    #[test]
+    fn find_and_replace() {
+        let mut c = controller("foo bar\nFoo baz foo");
+        let search = Search::new("foo", Default::default()).unwrap();
+        assert_eq!(c.find_matches(&search), vec!((0, 3), (8, 11), (16, 19)));
+        c.cursor_right(false);
+        assert!(c.find_next(&search, false));
+        assert_eq!(c.get_selection(), Some((8, 11)));
+        c.find_next(&search, true);
+        c.find_next(&search, true);
+        assert_eq!(c.get_selection(), Some((16, 19)));
+
+        c.replace_next(&search, "x");
+        assert_eq!(c.get_raw_text(), "foo bar\nFoo baz x");
+        assert_eq!(c.get_selection(), Some((0, 3)));
+        assert_eq!(c.replace_all(&search, "y"), 2);
+        assert_eq!(c.get_raw_text(), "y bar\ny baz x");
+        c.undo();
+        assert_eq!(c.get_raw_text(), "foo bar\nFoo baz x");
+    }
+
+    #[test]
+    fn multiple_cursors() {
+        let mut c = controller("ab\ncd\nef");
+        c.mouse_click(9, 0);
+        c.add_cursor(9, 20);
+        c.add_cursor(9, 40);
+        c.key_char('x');
+        assert_eq!(c.get_raw_text(), "axb\ncxd\nexf");
+        c.key_backspace();
+        c.key_backspace();
+        assert_eq!(c.get_raw_text(), "b\nd\nf");
+        c.key_enter();
+        assert_eq!(c.get_raw_text(), "\nb\n\nd\n\nf");
+        assert_eq!(c.get_carets(), vec!((0, 100), (0, 20), (0, 60)));
+        c.undo();
+        assert_eq!(c.get_raw_text(), "b\nd\nf");
+        assert_eq!(c.get_carets().len(), 1);
+
+        let mut c = controller("foo bar foo\nfoo");
+        c.add_next_occurrence();
+        assert_eq!(c.get_selected_text().as_deref(), Some("foo"));
+        c.add_next_occurrence();
+        c.add_next_occurrence();
+        assert_eq!(c.get_selections(), vec!((0, 3), (8, 11), (12, 15)));
+        type_str(&mut c, "ba");
+        assert_eq!(c.get_raw_text(), "ba bar ba\nba");
+        c.cursor_left(true);
+        c.key_delete();
+        assert_eq!(c.get_raw_text(), "b bar b\nb");
+        c.collapse_cursors();
+        assert_eq!(c.get_carets(), vec!((9, 20)));
+    }
+
+    #[test]
+    fn multiple_cursors_overlapping_and_undo() {
+        // the selections (1, 3) and (2, 4) overlap and are deleted as one range
+        let mut c = controller("abcdef");
+        c.mouse_click(9, 0);
+        c.add_cursor(18, 0);
+        c.cursor_right(true);
+        c.cursor_right(true);
+        assert_eq!(c.get_selections(), vec!((1, 3), (2, 4)));
+        c.key_delete();
+        assert_eq!(c.get_raw_text(), "aef");
+        assert_eq!(c.get_carets(), vec!((9, 0)));
+
+        // typing at several carets is undone in one step
+        let mut c = controller("ab\ncd");
+        c.mouse_click(9, 0);
+        c.add_cursor(9, 20);
+        type_str(&mut c, "xy");
+        assert_eq!(c.get_raw_text(), "axyb\ncxyd");
+        c.undo();
+        assert_eq!(c.get_raw_text(), "ab\ncd");
+        c.redo();
+        assert_eq!(c.get_raw_text(), "axyb\ncxyd");
+    }
+
+    #[test]
+    fn mouse_selection() {
+        let mut c = controller("foo(bar_baz)\nx = 1");
+        c.mouse_click(9, 0);
+        c.mouse_select(40, 25);
+        assert_eq!(c.get_selection(), Some((1, 17)));
+        c.mouse_select(0, 0);
+        assert_eq!(c.get_selection(), Some((0, 1)));
+        c.mouse_double_click(50, 5);
+        assert_eq!(c.get_selected_text().as_deref(), Some("bar_baz"));
+        c.mouse_double_click(27, 5);
+        assert_eq!(c.get_selected_text().as_deref(), Some("foo"));
+        c.mouse_triple_click(20, 0);
+        assert_eq!(c.get_selected_text().as_deref(), Some("foo(bar_baz)\n"));
+        c.mouse_triple_click(20, 20);
+        assert_eq!(c.get_selected_text().as_deref(), Some("x = 1"));
+        // dragging below the last line selects to its end
+        c.mouse_click(0, 0);
+        c.mouse_select(500, 300);
+        assert_eq!(c.get_selection(), Some((0, 18)));
+    }
+
+    #[test]
+    fn box_selection() {
+        // "i" is 3px wide, the box from 9px to 18px covers a different number of chars per line
+        let content = test_content("abcd\niiiiiiii\na\nabcd", CharWidths::monospace(0.6).with_width('i', 0.2));
+        let mut c = Controller::from_content(content);
+        c.box_select((9, 5), (18, 45));
+        assert_eq!(c.get_selections(), vec!((1, 2), (8, 11)));
+        assert_eq!(c.get_carets(), vec!((9, 40), (18, 0), (18, 20)));
+        c.key_char('x');
+        assert_eq!(c.get_raw_text(), "axcd\niiixii\nax\nabcd");
+
+        let mut c = controller("abc\nabc\nabc");
+        c.mouse_click(9, 0);
+        c.box_select_down();
+        c.box_select_down();
+        c.box_select_right();
+        assert_eq!(c.get_selections(), vec!((1, 2), (5, 6), (9, 10)));
+        c.box_select_up();
+        assert_eq!(c.get_selections(), vec!((1, 2), (5, 6)));
+        c.cursor_right(false);
+        assert_eq!(c.get_carets(), vec!((18, 20), (18, 0)));
+    }
+
+    #[test]
+    fn line_versions() {
+        let mut c = controller("ab\ncd\nef");
+        let versions = |c: &Controller| (0..c.get_content().num_lines()).map(|y| c.get_content().line_version(y)).collect::<Vec<_>>();
+        let before = versions(&c);
+        c.mouse_click(9, 20);
+        c.key_char('x');
+        let after = versions(&c);
+        assert_eq!((after[0], after[2]), (before[0], before[2]));
+        assert_ne!(after[1], before[1]);
+        c.key_enter();
+        let split = versions(&c);
+        assert_eq!((split[0], split[3]), (before[0], before[2]));
+        assert!(!split[1..3].contains(&after[1]));
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut c = controller("");
+        type_str(&mut c, "abc");
+        c.key_enter();
+        type_str(&mut c, "d");
+        c.undo();
+        assert_eq!(c.get_raw_text(), "abc\n");
+        c.undo();
+        assert_eq!(c.get_raw_text(), "abc");
+        c.undo();
+        assert_eq!(c.get_raw_text(), "");
+        c.redo();
+        assert_eq!(c.get_raw_text(), "abc");
+        assert_eq!(c.get_x(), 27);
+
+        c.cursor_left(false);
+        c.key_backspace();
+        c.undo();
+        assert_eq!(c.get_raw_text(), "abc");
+        assert_eq!(c.get_x(), 18);
+    }
+}
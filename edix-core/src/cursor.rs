@@ -0,0 +1,316 @@
+/*
+
+Cursor:
+- y: usize (simple as that)
+- x:
+  - character-index: usize
+  - pixel_pos: usize
+
+Movement events:
+- Left/Right: decrement/increment character-index and update pixel_pos
+- Up/down: decrement/increment y and update character-index (using new line and pixel_pos)
+- MouseClick: update y from mouse_y; set pixel_pos to mouse_x; update character-index from pixel_pos
+
+Selection:
+- anchor: Option<(y, character-index)>, the fixed end of the selection
+- the cursor position itself is the head (moving end) of the selection
+- no anchor or anchor == head means there's no selection
+*/
+
+use crate::content::TextBackend;
+use crate::words::{next_word_end, prev_word_start, word_around};
+
+
+#[derive(Clone, Default)]
+pub struct Cursor {
+    y: usize,
+    x_idx: usize,  // counts in characters
+    x_px: usize,   // counts in pixels
+    anchor: Option<(usize, usize)>,  // (y, x_idx) of the selection anchor
+}
+
+impl Cursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_x<T: TextBackend>(&self, content: &T) -> usize {
+        content.width_of_line(self.y, self.x_idx)
+    }
+    
+    pub fn get_y<T: TextBackend>(&self, content: &T) -> usize {
+        self.y * content.line_height()
+    }
+
+    pub fn get_idx<T: TextBackend>(&self, content: &T) -> usize {
+        Self::idx_of(content, self.y, self.x_idx)
+    }
+
+    pub fn set_idx<T: TextBackend>(&mut self, content: &T, idx: usize) {
+        self.y = content.char_to_line(idx);
+        self.x_idx = (idx - content.line_to_char(self.y)).min(content.num_chars_of_line(self.y));
+        self.update_x_px(content);
+    }
+
+    pub fn set_anchor(&mut self) {
+        if self.anchor.is_none() {
+            self.anchor = Some((self.y, self.x_idx));
+        }
+    }
+
+    pub fn clear_anchor(&mut self) {
+        self.anchor = None;
+    }
+
+    // char index range `from..to` of the current selection, if it isn't empty
+    pub fn get_selection<T: TextBackend>(&self, content: &T) -> Option<(usize, usize)> {
+        let (anchor_y, anchor_x) = self.anchor?;
+        let anchor = Self::idx_of(content, anchor_y, anchor_x);
+        let head = self.get_idx(content);
+        if anchor < head {
+            Some((anchor, head))
+        } else if head < anchor {
+            Some((head, anchor))
+        } else {
+            None
+        }
+    }
+
+    fn idx_of<T: TextBackend>(content: &T, y: usize, x_idx: usize) -> usize {
+        content.line_to_char(y) + x_idx
+    }
+
+    pub fn cursor_up<T: TextBackend>(&mut self, content: &T) {
+        if self.y > 0 {
+            self.y -= 1;
+            self.update_x_idx(content);
+        } else {
+            self.x_idx = 0;
+            self.x_px = 0;
+        }
+    }
+
+    pub fn cursor_down<T: TextBackend>(&mut self, content: &T) {
+        if self.y < content.num_lines() - 1 {
+            self.y += 1;
+            self.update_x_idx(content);
+        } else {
+            self.x_idx = content.num_chars_of_line(self.y);
+            self.update_x_px(content);
+        }
+    }
+
+    pub fn cursor_left<T: TextBackend>(&mut self, content: &T) {
+        if self.x_idx > 0 {
+            self.x_idx -= 1;
+            self.update_x_px(content);
+        } else if self.y > 0 {
+            self.y -= 1;
+            self.x_idx = content.num_chars_of_line(self.y);
+            self.update_x_px(content);
+        }
+    }
+
+    pub fn cursor_right<T: TextBackend>(&mut self, content: &T) {
+        if self.x_idx < content.num_chars_of_line(self.y) {
+            self.x_idx += 1;
+            self.update_x_px(content);
+        } else if self.y < content.num_lines() - 1 {
+            self.y += 1;
+            self.x_idx = 0;
+            self.update_x_px(content);
+        }
+    }
+
+    pub fn cursor_word_left<T: TextBackend>(&mut self, content: &T) {
+        if self.x_idx == 0 {
+            self.cursor_left(content);
+        } else {
+            self.x_idx = prev_word_start(&content.line_chars(self.y), &content.token_starts(self.y), self.x_idx);
+            self.update_x_px(content);
+        }
+    }
+
+    pub fn cursor_word_right<T: TextBackend>(&mut self, content: &T) {
+        if self.x_idx == content.num_chars_of_line(self.y) {
+            self.cursor_right(content);
+        } else {
+            self.x_idx = next_word_end(&content.line_chars(self.y), &content.token_starts(self.y), self.x_idx);
+            self.update_x_px(content);
+        }
+    }
+
+    pub fn cursor_home<T: TextBackend>(&mut self, content: &T) {
+        self.x_idx = 0;
+        self.update_x_px(content);
+    }
+
+    pub fn cursor_end<T: TextBackend>(&mut self, content: &T) {
+        self.x_idx = content.num_chars_of_line(self.y);
+        self.update_x_px(content);
+    }
+
+    // clicks below the last line hit the last line
+    pub fn mouse_click<T: TextBackend>(&mut self, content: &T, x: usize, y: usize) {
+        self.y = (y / content.line_height()).min(content.num_lines() - 1);
+        self.x_px = x;
+        self.update_x_idx(content);
+        self.update_x_px(content);
+    }
+
+    // char index range of the word at the cursor, see `words::word_around`
+    pub fn word_range<T: TextBackend>(&self, content: &T) -> (usize, usize) {
+        let (from, to) = word_around(&content.line_chars(self.y), &content.token_starts(self.y), self.x_idx);
+        let line_start = content.line_to_char(self.y);
+        (line_start + from, line_start + to)
+    }
+
+    // char index range of the cursor's line, including its line break
+    pub fn line_range<T: TextBackend>(&self, content: &T) -> (usize, usize) {
+        let to = if self.y + 1 < content.num_lines() {
+            content.line_to_char(self.y + 1)
+        } else {
+            content.line_to_char(self.y) + content.num_chars_of_line(self.y)
+        };
+        (content.line_to_char(self.y), to)
+    }
+
+    fn update_x_idx<T: TextBackend>(&mut self, content: &T) {
+        let widths = content.widths_of_line(self.y);
+        // first char boundary at or right of x_px, or the one before it if that's closer
+        let i = widths.partition_point(|w| *w < self.x_px);
+        let prev_is_closer = |i: usize| i > 0 && self.x_px - widths[i-1] <= widths[i] - self.x_px;
+        self.x_idx = if i == widths.len() || prev_is_closer(i) { i - 1 } else { i };
+    }
+
+    fn update_x_px<T: TextBackend>(&mut self, content: &T) {
+        self.x_px = content.width_of_line(self.y, self.x_idx);
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::test_content;
+    use crate::fonts::Font;
+    use crate::measure::{CharWidths, Measure};
+    use syntect::highlighting::FontStyle;
+
+    // 15px font, so chars are 9px wide
+    fn mono() -> CharWidths {
+        CharWidths::monospace(0.6)
+    }
+
+    fn pos(cursor: &Cursor) -> (usize, usize) {
+        (cursor.y, cursor.x_idx)
+    }
+
+    #[test]
+    fn left_right_wrap_lines() {
+        let content = test_content("ab\ncd", mono());
+        let mut cursor = Cursor::new();
+        cursor.cursor_left(&content);
+        assert_eq!(pos(&cursor), (0, 0));
+        for _ in 0..3 {
+            cursor.cursor_right(&content);
+        }
+        assert_eq!(pos(&cursor), (1, 0));
+        assert_eq!(cursor.get_idx(&content), 3);
+        cursor.cursor_left(&content);
+        assert_eq!(pos(&cursor), (0, 2));
+        for _ in 0..10 {
+            cursor.cursor_right(&content);
+        }
+        assert_eq!(pos(&cursor), (1, 2));
+    }
+
+    #[test]
+    fn tabs_advance_to_tab_stops() {
+        // tab stops are 4 cells of 9px apart, regardless of the width of the other chars
+        let content = test_content("a\tb\n\tx\t", mono().with_width('a', 1.0));
+        let widths: Vec<usize> = (0..=3).map(|x| content.width_of_line(0, x)).collect();
+        assert_eq!(widths, vec!(0, 15, 36, 45));
+        let widths: Vec<usize> = (0..=3).map(|x| content.width_of_line(1, x)).collect();
+        assert_eq!(widths, vec!(0, 36, 45, 72));
+    }
+
+    // 9px per char, but "AV" is kerned by 4px
+    struct Kerned;
+
+    impl Measure for Kerned {
+        fn width(&self, font: &Font, font_style: FontStyle, text: &str) -> f64 {
+            mono().width(font, font_style, text) - 4.0 * text.matches("AV").count() as f64
+        }
+    }
+
+    #[test]
+    fn runs_are_measured_with_kerning() {
+        // prefixes are measured as a whole up to the next tab
+        let content = test_content("AVAV\tAV", Kerned);
+        let widths: Vec<usize> = (0..=7).map(|x| content.width_of_line(0, x)).collect();
+        assert_eq!(widths, vec!(0, 9, 14, 23, 28, 36, 45, 50));
+        let mut cursor = Cursor::new();
+        cursor.mouse_click(&content, 26, 0);
+        assert_eq!(pos(&cursor), (0, 4));
+    }
+
+    #[test]
+    fn up_down_keep_pixel_position() {
+        let content = test_content("abcdef\nab\nabcdef", mono());
+        let mut cursor = Cursor::new();
+        cursor.set_idx(&content, 5);
+        cursor.cursor_down(&content);
+        assert_eq!(pos(&cursor), (1, 2));
+        cursor.cursor_down(&content);
+        assert_eq!(pos(&cursor), (2, 5));
+        cursor.cursor_down(&content);
+        assert_eq!(pos(&cursor), (2, 6));
+        cursor.cursor_up(&content);
+        cursor.cursor_up(&content);
+        cursor.cursor_up(&content);
+        assert_eq!(pos(&cursor), (0, 0));
+    }
+
+    #[test]
+    fn up_down_in_proportional_text() {
+        // "i" is a third as wide as "m": 3px vs 9px
+        let content = test_content("iiiiii\nmm", mono().with_width('i', 0.2));
+        let mut cursor = Cursor::new();
+        cursor.set_idx(&content, 6);
+        assert_eq!(cursor.get_x(&content), 18);
+        cursor.cursor_down(&content);
+        assert_eq!(pos(&cursor), (1, 2));
+        cursor.cursor_up(&content);
+        assert_eq!(pos(&cursor), (0, 6));
+    }
+
+    #[test]
+    fn mouse_click_snaps_to_nearest_char_boundary() {
+        let content = test_content("abcdef\nabcdef", mono());
+        let mut cursor = Cursor::new();
+        cursor.mouse_click(&content, 22, 25);
+        assert_eq!(pos(&cursor), (1, 2));
+        assert_eq!(cursor.get_x(&content), 18);
+        cursor.mouse_click(&content, 23, 5);
+        assert_eq!(pos(&cursor), (0, 3));
+        cursor.mouse_click(&content, 500, 0);
+        assert_eq!(pos(&cursor), (0, 6));
+        cursor.mouse_click(&content, 10, 500);
+        assert_eq!(pos(&cursor), (1, 1));
+    }
+
+    #[test]
+    fn selection_is_ordered() {
+        let content = test_content("abc\ndef", mono());
+        let mut cursor = Cursor::new();
+        cursor.set_idx(&content, 5);
+        assert_eq!(cursor.get_selection(&content), None);
+        cursor.set_anchor();
+        cursor.cursor_up(&content);
+        assert_eq!(cursor.get_selection(&content), Some((1, 5)));
+        cursor.clear_anchor();
+        assert_eq!(cursor.get_selection(&content), None);
+    }
+}
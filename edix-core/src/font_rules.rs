@@ -0,0 +1,143 @@
+/*
+
+Font rules:
+- each rule maps a syntect scope selector (https://www.sublimetext.com/docs/3/selectors.html) to a font role
+  (see `fonts`), e.g. "comment.line.documentation" to "docs" while other comments use "prose"
+- selectors are matched against the full scope stack of a token, so they support descendant
+  selectors ("source.python variable"), alternatives ("comment, string") and exclusions
+  ("constant - constant.character.escape")
+- like in color themes, the rule matching deepest into the scope stack wins; on ties, later rules win
+- tokens that aren't matched by any rule use the `DEFAULT_ROLE`
+
+*/
+
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use syntect::highlighting::ScopeSelectors;
+use syntect::parsing::{ParseScopeError, ScopeStack};
+use crate::fonts::DEFAULT_ROLE;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FontRule {
+    pub selector: String,
+    #[serde(alias = "font")]
+    pub role: String,
+}
+
+impl FontRule {
+    pub fn new(selector: &str, role: &str) -> Self {
+        Self {
+            selector: selector.to_string(),
+            role: role.to_string(),
+        }
+    }
+}
+
+// description of scopes: https://www.sublimetext.com/docs/3/scope_naming.html
+pub fn default_font_rules() -> Vec<FontRule> {
+    vec!(
+        FontRule::new(
+            "comment, string - constant.character.escape, constant - constant.character.escape, \
+             entity, variable, meta.generic-name, support",
+            "prose"
+        ),
+        FontRule::new("punctuation, keyword, storage", "code"),
+    )
+}
+
+// font rules with their selectors parsed
+#[derive(Clone)]
+pub struct FontRules {
+    rules: Vec<FontRule>,
+    selectors: Vec<ScopeSelectors>,
+}
+
+impl FontRules {
+    pub fn new(rules: Vec<FontRule>) -> Result<Self, ParseScopeError> {
+        let selectors = rules.iter()
+            .map(|r| ScopeSelectors::from_str(&r.selector))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules, selectors })
+    }
+
+    pub fn get_rules(&self) -> &[FontRule] {
+        &self.rules
+    }
+
+    pub fn role_for_stack(&self, stack: &ScopeStack) -> &str {
+        let mut best = None;
+        for (rule, selector) in self.rules.iter().zip(self.selectors.iter()) {
+            if let Some(power) = selector.does_match(stack.as_slice()) {
+                if best.map(|(best_power, _)| power >= best_power).unwrap_or(true) {
+                    best = Some((power, &rule.role));
+                }
+            }
+        }
+        best.map(|(_, role)| role.as_str()).unwrap_or(DEFAULT_ROLE)
+    }
+}
+
+impl Default for FontRules {
+    fn default() -> Self {
+        Self::new(default_font_rules()).expect("default font rules are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn role(rules: &FontRules, stack: &str) -> String {
+        rules.role_for_stack(&ScopeStack::from_str(stack).unwrap()).to_string()
+    }
+
+    // the hard-coded check the default rules replaced, applied to the innermost scope
+    fn scope_is_proportional(s: &str) -> bool {
+        s.starts_with("comment")
+        || s.starts_with("string")
+        || s.starts_with("constant") && !s.starts_with("constant.character.escape")
+        || s.starts_with("entity")
+        || s.starts_with("variable")
+        || s.starts_with("meta.generic-name")
+        || s.starts_with("support")
+    }
+
+    #[test]
+    fn default_rules_match_scope_is_proportional() {
+        let rules = FontRules::default();
+        for scope in &[
+            "comment.line.double-slash.rust", "string.quoted.double.rust", "constant.numeric.integer",
+            "constant.character.escape.rust", "entity.name.function.rust", "variable.parameter",
+            "meta.generic-name.haskell", "support.type.rust", "keyword.control.rust",
+            "storage.type.rust", "punctuation.separator.rust", "meta.block.rust", "source.rust",
+        ] {
+            let expected = if scope_is_proportional(scope) { "prose" } else { DEFAULT_ROLE };
+            assert_eq!(role(&rules, scope), expected, "{}", scope);
+        }
+    }
+
+    #[test]
+    fn exclusions_and_nesting() {
+        let rules = FontRules::default();
+        assert_eq!(role(&rules, "source.rust string.quoted.double.rust"), "prose");
+        assert_eq!(role(&rules, "source.rust string.quoted.double.rust constant.character.escape.rust"), "code");
+        // the innermost match wins over an enclosing one
+        assert_eq!(role(&rules, "source.rust comment.block.rust punctuation.definition.comment.rust"), "code");
+        assert_eq!(role(&rules, "source.rust meta.block.rust"), DEFAULT_ROLE);
+    }
+
+    #[test]
+    fn ties_go_to_later_rules() {
+        let rules = FontRules::new(vec!(
+            FontRule::new("comment", "prose"),
+            FontRule::new("comment.line", "docs"),
+            FontRule::new("string", "prose"),
+            FontRule::new("string", "code"),
+        )).unwrap();
+        // a more specific selector wins regardless of its position ...
+        assert_eq!(role(&rules, "comment.line.double-slash"), "docs");
+        assert_eq!(role(&rules, "comment.block"), "prose");
+        // ... while selectors matching equally deep are resolved by order
+        assert_eq!(role(&rules, "string.quoted"), "code");
+    }
+}
@@ -0,0 +1,84 @@
+/*
+
+Fonts:
+- font rules (see `font_rules`) map scopes to font roles like "code" or "prose"
+- a font profile assigns a concrete font (family, size, weight, letter-spacing) to each role
+- roles that aren't defined in a profile fall back to the profile's `DEFAULT_ROLE`
+
+*/
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_ROLE: &str = "code";
+
+const MONO: &str = "\"Fira Code\", monospace";
+const SANS: &str = "\"Fira\", sans-serif";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Font {
+    pub family: String,
+    pub size: usize,          // in px
+    pub weight: u16,
+    pub letter_spacing: f32,  // in px
+}
+
+impl Font {
+    pub fn new(family: &str, size: usize) -> Self {
+        Self {
+            family: family.to_string(),
+            size,
+            weight: 400,
+            letter_spacing: 0.0,
+        }
+    }
+
+    pub fn to_css(&self) -> String {
+        format!(
+            "font-family: {}; font-size: {}px; font-weight: {}; letter-spacing: {}px;",
+            self.family, self.size, self.weight, self.letter_spacing
+        )
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font::new(MONO, 15)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct FontProfile {
+    pub name: String,
+    pub roles: BTreeMap<String, Font>,
+}
+
+impl FontProfile {
+    pub fn new(name: &str, roles: Vec<(&str, Font)>) -> Self {
+        Self {
+            name: name.to_string(),
+            roles: roles.into_iter().map(|(role, font)| (role.to_string(), font)).collect(),
+        }
+    }
+
+    pub fn font_for_role(&self, role: &str) -> Option<&Font> {
+        self.roles.get(role).or_else(|| self.roles.get(DEFAULT_ROLE))
+    }
+}
+
+pub fn default_font_profiles() -> Vec<FontProfile> {
+    vec!(
+        FontProfile::new("Monospace", vec!(
+            ("code", Font::new(MONO, 15)),
+            ("prose", Font::new(MONO, 15)),
+        )),
+        FontProfile::new("Sans", vec!(
+            ("code", Font::new(SANS, 15)),
+            ("prose", Font::new(SANS, 15)),
+        )),
+        FontProfile::new("Mixed", vec!(
+            ("code", Font::new(MONO, 15)),
+            ("prose", Font::new(SANS, 15)),
+        )),
+    )
+}
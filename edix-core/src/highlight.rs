@@ -0,0 +1,106 @@
+
+use crate::content::Region;
+use lazy_static::lazy_static;
+use syntect::parsing::SyntaxSet;
+use syntect::highlighting::{ThemeSet, Style, Highlighter};
+use syntect::highlighting::{
+    HighlightState, RangedHighlightIterator
+};
+use syntect::parsing::{
+    ParseState, ScopeStack
+};
+use syntect::parsing::ScopedMetadata;
+use crate::content::RichContentOptions;
+
+lazy_static!(
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+);
+
+// parser and highlighter state at the start of a line
+#[derive(Clone, PartialEq, Eq)]
+pub struct LineState {
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+}
+
+// state at the start of the document
+pub fn initial_state(options: &RichContentOptions) -> LineState {
+    let syntax = SYNTAX_SET.find_syntax_by_name(&options.selected_syntax).unwrap();
+    let highlighter = Highlighter::new(&THEME_SET.themes[&options.selected_theme]);
+    LineState {
+        parse_state: ParseState::new(syntax),
+        highlight_state: HighlightState::new(&highlighter, ScopeStack::new()),
+    }
+}
+
+// indentation rules and comment markers of the selected syntax
+pub fn syntax_metadata(options: &RichContentOptions) -> ScopedMetadata<'static> {
+    let syntax = SYNTAX_SET.find_syntax_by_name(&options.selected_syntax).unwrap();
+    SYNTAX_SET.metadata().metadata_for_scope(&[syntax.scope])
+}
+
+// a piece of a line together with its theme style and the full scope stack it's in
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub style: Style,
+    pub scopes: ScopeStack,
+}
+
+// parses and styles a line in a single pass and advances `state` to the start of the next line
+pub fn tokenize_line<'a>(line: &'a str, state: &mut LineState, highlighter: &Highlighter) -> Vec<Token<'a>> {
+    let ops = state.parse_state.parse_line(line, &SYNTAX_SET);
+
+    // the iterator applies the op at index `i` after yielding the text ending at `i`, so the
+    // scope stack of a token consists of all ops up to (and including) the token's start
+    let mut path = state.highlight_state.path.clone();
+    let mut next_op = 0;
+    let iter = RangedHighlightIterator::new(&mut state.highlight_state, &ops[..], line, highlighter);
+    iter.map(|(style, text, range)| {
+        while next_op < ops.len() && ops[next_op].0 <= range.start {
+            path.apply(&ops[next_op].1);
+            next_op += 1;
+        }
+        Token { text, style, scopes: path.clone() }
+    }).collect()
+}
+
+// highlights a single line (including its trailing '\n') and advances `state` to the start of the next line
+pub fn highlight_line(line: &str, state: &mut LineState, options: &RichContentOptions) -> Vec<Region> {
+    if line.is_empty() {
+        return vec!();
+    }
+    let highlighter = Highlighter::new(&THEME_SET.themes[&options.selected_theme]);
+
+    tokenize_line(line, state, &highlighter).into_iter().map(|token| {
+        let scope = match token.scopes.scopes.last() {
+            Some(x) => format!("{}", x),
+            None => String::new(),
+        };
+        let role = options.font_rules.role_for_stack(&token.scopes);
+        Region::new(
+            token.text.replace('\n', ""), 
+            style_to_css(&token.style),
+            options.font_profile().font_for_role(role).cloned().unwrap_or_default(),
+            token.style.font_style,
+            format!("{} ({})", scope, role)
+        )
+    }).collect()
+}
+
+pub fn to_html_color(c: &syntect::highlighting::Color) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a)
+}
+
+pub fn selection_color(theme: &str) -> String {
+    THEME_SET.themes[theme].settings.selection.as_ref().map(to_html_color).unwrap_or_else(|| "#b4d5fe80".to_string())
+}
+
+// background of search matches, drawn on top of the text
+pub fn find_highlight_color(theme: &str) -> String {
+    THEME_SET.themes[theme].settings.find_highlight.as_ref().map(to_html_color).unwrap_or_else(|| "#ffe79280".to_string())
+}
+
+fn style_to_css(sty: &Style) -> String {
+    format!("color: {}; background-color: {}; ", to_html_color(&sty.foreground), to_html_color(&sty.background))
+}
@@ -0,0 +1,85 @@
+/*
+
+History:
+- every edit of the content is recorded as a `Change` (replace `removed` by `inserted` at `from`)
+- undo applies the inverse change, redo applies the change again
+- consecutive typed characters are merged into a single change so that they're undone in one step,
+  also when typing at multiple cursors (the change then spans the text between the cursors)
+- cursor positions (char indices) before and after the change are stored to restore the cursor
+
+*/
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub from: usize,
+    pub removed: String,
+    pub inserted: String,
+    pub cursor_before: usize,
+    pub cursor_after: usize,
+}
+
+impl Change {
+    // end of the inserted text, i.e. the range `from..inserted_end` is replaced on undo
+    pub fn inserted_end(&self) -> usize {
+        self.from + self.inserted.chars().count()
+    }
+
+    pub fn removed_end(&self) -> usize {
+        self.from + self.removed.chars().count()
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Change>,
+    redo_stack: Vec<Change>,
+    // whether the next typed character may be merged into the last change
+    typing: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // records a change. `typing` changes directly following each other are grouped if the
+    // later one only changes text inserted by the earlier one.
+    pub fn record(&mut self, change: Change, typing: bool) {
+        self.redo_stack.clear();
+        if typing && self.typing {
+            if let Some(last) = self.undo_stack.last_mut() {
+                if last.from <= change.from && change.removed_end() <= last.inserted_end() {
+                    let start = change.from - last.from;
+                    let end = change.removed_end() - last.from;
+                    last.inserted = last.inserted.chars().take(start)
+                        .chain(change.inserted.chars())
+                        .chain(last.inserted.chars().skip(end))
+                        .collect();
+                    last.cursor_after = change.cursor_after;
+                    return;
+                }
+            }
+        }
+        self.undo_stack.push(change);
+        self.typing = typing;
+    }
+
+    // ends the current group of typed characters
+    pub fn seal(&mut self) {
+        self.typing = false;
+    }
+
+    pub fn undo(&mut self) -> Option<Change> {
+        self.typing = false;
+        let change = self.undo_stack.pop()?;
+        self.redo_stack.push(change.clone());
+        Some(change)
+    }
+
+    pub fn redo(&mut self) -> Option<Change> {
+        self.typing = false;
+        let change = self.redo_stack.pop()?;
+        self.undo_stack.push(change.clone());
+        Some(change)
+    }
+}
@@ -0,0 +1,118 @@
+/*
+
+Automatic indentation:
+- a new line starts with the leading whitespace of the line it's broken from
+- the selected syntax's indentation rules (from its `.tmPreferences` metadata) adjust it:
+  - `increaseIndentPattern` (e.g. a trailing ':' in Python or '{' in C) indents the next line
+  - `bracketIndentNextLinePattern` (e.g. `if (x)` without braces) indents only the next line
+- breaking a line between brackets like "{|}" puts the closing bracket on its own line
+- typing a char that makes a line match `decreaseIndentPattern` (e.g. '}' or "else:")
+  dedents it by one level, if it still has the automatic indentation
+- one level of indentation is the configured `IndentUnit`, either a tab or a number of spaces;
+  tabs are `width` columns wide when computing indentation columns
+
+*/
+
+use crate::content::RichContentOptions;
+use crate::highlight::syntax_metadata;
+use serde::{Deserialize, Serialize};
+use syntect::parsing::ScopedMetadata;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndentUnit {
+    pub use_tabs: bool,
+    pub width: usize,
+}
+
+impl IndentUnit {
+    // a width of 0 would make every column an indentation stop
+    fn width(&self) -> usize {
+        self.width.max(1)
+    }
+
+    // the text of one indentation level
+    pub fn text(&self) -> String {
+        if self.use_tabs { "\t".to_string() } else { " ".repeat(self.width()) }
+    }
+
+    // whitespace from column `col` to the next indentation stop
+    pub fn text_from(&self, col: usize) -> String {
+        if self.use_tabs { "\t".to_string() } else { " ".repeat(self.width() - col % self.width()) }
+    }
+
+    // column after the whitespace `s`
+    pub fn columns(&self, s: &str) -> usize {
+        s.chars().fold(0, |col, c| if c == '\t' { col + self.width() - col % self.width() } else { col + 1 })
+    }
+
+    // `indent` with one indentation level removed
+    pub fn dedent<'a>(&self, indent: &'a str) -> &'a str {
+        if let Some(rest) = indent.strip_suffix('\t') {
+            return rest;
+        }
+        let spaces = indent.len() - indent.trim_end_matches(' ').len();
+        &indent[..indent.len() - spaces.min(self.width())]
+    }
+
+    // `indent` with the whitespace back to the previous indentation stop removed
+    pub fn dedent_to_stop<'a>(&self, indent: &'a str) -> &'a str {
+        if let Some(rest) = indent.strip_suffix('\t') {
+            return rest;
+        }
+        let spaces = indent.len() - indent.trim_end_matches(' ').len();
+        let over = self.columns(indent) % self.width();
+        let remove = if over == 0 { self.width() } else { over };
+        &indent[..indent.len() - spaces.min(remove)]
+    }
+}
+
+impl Default for IndentUnit {
+    fn default() -> Self {
+        Self { use_tabs: false, width: 4 }
+    }
+}
+
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+// indentation of the line following `line`, `prev` is the line above `line`
+fn next_indent(metadata: &ScopedMetadata, unit: IndentUnit, prev: Option<&str>, line: &str) -> String {
+    let indent = leading_whitespace(line);
+    if metadata.increase_indent(line) || metadata.bracket_increase(line) {
+        format!("{}{}", indent, unit.text())
+    } else if prev.map(|p| metadata.bracket_increase(p) && !metadata.increase_indent(p)) == Some(true) {
+        // the single indented line after e.g. `if (x)` is done
+        unit.dedent(indent).to_string()
+    } else {
+        indent.to_string()
+    }
+}
+
+// text replacing the cursor (or selection) on Enter and the cursor offset within it.
+// `before` and `after` are the parts of the line around it, `prev` is the line above.
+pub fn line_break(prev: Option<&str>, before: &str, after: &str, options: &RichContentOptions) -> (String, usize) {
+    let metadata = syntax_metadata(options);
+    let indent = next_indent(&metadata, options.indent_unit, prev, before);
+    let text = format!("\n{}", indent);
+    let cursor = text.chars().count();
+    let base = leading_whitespace(before);
+    if indent.len() > base.len() && metadata.decrease_indent(after) {
+        return (format!("{}\n{}", text, base), cursor);
+    }
+    (text, cursor)
+}
+
+// new indentation of `line` if typing a char (which turned `old_line` into `line`) should
+// dedent it. `prev` is the closest non-blank line above and `prev_prev` the line above that.
+pub fn electric_indent(prev_prev: Option<&str>, prev: &str, old_line: &str, line: &str, options: &RichContentOptions) -> Option<String> {
+    let metadata = syntax_metadata(options);
+    if !metadata.decrease_indent(line) || metadata.decrease_indent(old_line) {
+        return None;
+    }
+    let expected = next_indent(&metadata, options.indent_unit, prev_prev, prev);
+    if leading_whitespace(line) != expected {
+        return None;
+    }
+    Some(options.indent_unit.dedent(&expected).to_string())
+}
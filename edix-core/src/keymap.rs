@@ -0,0 +1,200 @@
+/*
+
+Keymap:
+- maps key chords like "Ctrl+Shift+z" to named `Command`s that are executed by the `Controller`
+- chords are written as modifiers (in the order Ctrl, Alt, Shift, Meta) followed by the key name
+  as reported by the browser (`KeyboardEvent.key`), e.g. "ArrowLeft", "Ctrl+Backspace" or "Ctrl++"
+- single char keys are case-insensitive, "Shift+Z" and "Shift+z" are the same chord
+- user overrides are applied on top of the default bindings, binding a chord to `None` removes it
+
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Command {
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    CursorWordLeft,
+    CursorWordRight,
+    CursorHome,
+    CursorEnd,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    SelectWordLeft,
+    SelectWordRight,
+    SelectHome,
+    SelectEnd,
+    Backspace,
+    Delete,
+    WordBackspace,
+    WordDelete,
+    Enter,
+    Indent,
+    Dedent,
+    ToggleComment,
+    AddNextOccurrence,
+    CollapseCursors,
+    BoxSelectUp,
+    BoxSelectDown,
+    BoxSelectLeft,
+    BoxSelectRight,
+    // opens the find bar, handled by the frontend
+    Find,
+    Undo,
+    Redo,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    key: String,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+}
+
+impl KeyChord {
+    pub fn new(key: &str, ctrl: bool, alt: bool, shift: bool, meta: bool) -> Self {
+        let key = if key.chars().count() == 1 { key.to_lowercase() } else { key.to_string() };
+        Self { key, ctrl, alt, shift, meta }
+    }
+
+    // parses chords like "Ctrl+Shift+z"
+    pub fn parse(s: &str) -> Option<Self> {
+        let (mut ctrl, mut alt, mut shift, mut meta) = (false, false, false, false);
+        let mut rest = s;
+        loop {
+            let (modifier, tail) = match rest.find('+') {
+                Some(i) if i > 0 && i + 1 < rest.len() => (&rest[..i], &rest[i+1..]),
+                _ => break,
+            };
+            match modifier {
+                "Ctrl" => ctrl = true,
+                "Alt" => alt = true,
+                "Shift" => shift = true,
+                "Meta" => meta = true,
+                _ => return None,
+            }
+            rest = tail;
+        }
+        if rest.is_empty() || (rest.len() > 1 && rest.ends_with('+')) {
+            return None;
+        }
+        Some(Self::new(rest, ctrl, alt, shift, meta))
+    }
+}
+
+pub fn default_bindings() -> Vec<(&'static str, Command)> {
+    use Command::*;
+    vec!(
+        ("ArrowUp", CursorUp),
+        ("ArrowDown", CursorDown),
+        ("ArrowLeft", CursorLeft),
+        ("ArrowRight", CursorRight),
+        ("Ctrl+ArrowLeft", CursorWordLeft),
+        ("Ctrl+ArrowRight", CursorWordRight),
+        ("Home", CursorHome),
+        ("End", CursorEnd),
+        ("Shift+ArrowUp", SelectUp),
+        ("Shift+ArrowDown", SelectDown),
+        ("Shift+ArrowLeft", SelectLeft),
+        ("Shift+ArrowRight", SelectRight),
+        ("Ctrl+Shift+ArrowLeft", SelectWordLeft),
+        ("Ctrl+Shift+ArrowRight", SelectWordRight),
+        ("Shift+Home", SelectHome),
+        ("Shift+End", SelectEnd),
+        ("Backspace", Backspace),
+        ("Shift+Backspace", Backspace),
+        ("Delete", Delete),
+        ("Ctrl+Backspace", WordBackspace),
+        ("Ctrl+Delete", WordDelete),
+        ("Enter", Enter),
+        ("Shift+Enter", Enter),
+        ("Tab", Indent),
+        ("Shift+Tab", Dedent),
+        ("Ctrl+/", ToggleComment),
+        ("Meta+/", ToggleComment),
+        ("Ctrl+d", AddNextOccurrence),
+        ("Meta+d", AddNextOccurrence),
+        ("Escape", CollapseCursors),
+        ("Alt+Shift+ArrowUp", BoxSelectUp),
+        ("Alt+Shift+ArrowDown", BoxSelectDown),
+        ("Alt+Shift+ArrowLeft", BoxSelectLeft),
+        ("Alt+Shift+ArrowRight", BoxSelectRight),
+        ("Ctrl+f", Find),
+        ("Meta+f", Find),
+        ("Ctrl+z", Undo),
+        ("Meta+z", Undo),
+        ("Ctrl+Shift+z", Redo),
+        ("Meta+Shift+z", Redo),
+        ("Ctrl+y", Redo),
+    )
+}
+
+pub struct Keymap {
+    bindings: HashMap<KeyChord, Command>,
+    overrides: BTreeMap<String, Option<Command>>,
+}
+
+impl Keymap {
+    // default bindings with `overrides` applied, chords that can't be parsed are ignored
+    pub fn new(overrides: BTreeMap<String, Option<Command>>) -> Self {
+        let mut bindings: HashMap<KeyChord, Command> = default_bindings().into_iter()
+            .filter_map(|(chord, command)| Some((KeyChord::parse(chord)?, command)))
+            .collect();
+        for (chord, command) in overrides.iter() {
+            if let Some(chord) = KeyChord::parse(chord) {
+                match command {
+                    Some(command) => bindings.insert(chord, *command),
+                    None => bindings.remove(&chord),
+                };
+            }
+        }
+        Self { bindings, overrides }
+    }
+
+    pub fn get(&self, chord: &KeyChord) -> Option<Command> {
+        self.bindings.get(chord).copied()
+    }
+
+    pub fn get_overrides(&self) -> &BTreeMap<String, Option<Command>> {
+        &self.overrides
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(BTreeMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_chords() {
+        assert_eq!(KeyChord::parse("Ctrl+Shift+Z"), Some(KeyChord::new("z", true, false, true, false)));
+        assert_eq!(KeyChord::parse("Ctrl++"), Some(KeyChord::new("+", true, false, false, false)));
+        assert_eq!(KeyChord::parse("+"), Some(KeyChord::new("+", false, false, false, false)));
+        assert_eq!(KeyChord::parse("Hyper+a"), None);
+        assert_eq!(KeyChord::parse("Ctrl+"), None);
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("Ctrl+y".to_string(), None);
+        overrides.insert("Alt+ArrowLeft".to_string(), Some(Command::CursorWordLeft));
+        let keymap = Keymap::new(overrides);
+        assert_eq!(keymap.get(&KeyChord::new("Y", true, false, false, false)), None);
+        assert_eq!(keymap.get(&KeyChord::new("ArrowLeft", false, true, false, false)), Some(Command::CursorWordLeft));
+        assert_eq!(keymap.get(&KeyChord::new("Z", true, false, true, false)), Some(Command::Redo));
+    }
+}
@@ -0,0 +1,14 @@
+pub mod comment;
+pub mod cursor;
+pub mod content;
+pub mod controller;
+pub mod font_rules;
+pub mod fonts;
+pub mod highlight;
+pub mod history;
+pub mod indent;
+pub mod keymap;
+pub mod measure;
+pub mod search;
+pub mod vim;
+pub mod words;
@@ -0,0 +1,51 @@
+/*
+
+Text measurement:
+- `Content` measures the widths of its text using a `Measure` implementation, which is provided
+  by the frontend (e.g. using a canvas in the browser)
+- `CharWidths` is an implementation based on a table of per-char advance widths, which allows
+  using `Content` (and therefore `Cursor` and `Controller`) without any rendering backend
+
+*/
+
+use std::collections::HashMap;
+use syntect::highlighting::FontStyle;
+use crate::fonts::Font;
+
+pub trait Measure {
+    // width of `text` in px when rendered with `font` and `font_style`
+    fn width(&self, font: &Font, font_style: FontStyle, text: &str) -> f64;
+
+    // whether measurements are final and may be cached
+    fn is_ready(&self) -> bool {
+        true
+    }
+}
+
+// advance widths in em, i.e. relative to the font size
+pub struct CharWidths {
+    default: f64,
+    widths: HashMap<char, f64>,
+}
+
+impl CharWidths {
+    // all chars have the same width
+    pub fn monospace(width: f64) -> Self {
+        Self {
+            default: width,
+            widths: HashMap::new(),
+        }
+    }
+
+    pub fn with_width(mut self, c: char, width: f64) -> Self {
+        self.widths.insert(c, width);
+        self
+    }
+}
+
+impl Measure for CharWidths {
+    fn width(&self, font: &Font, _font_style: FontStyle, text: &str) -> f64 {
+        let ems: f64 = text.chars().map(|c| *self.widths.get(&c).unwrap_or(&self.default)).sum();
+        ems * font.size as f64
+    }
+}
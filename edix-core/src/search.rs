@@ -0,0 +1,104 @@
+/*
+
+Search:
+- a pattern is searched for either literally or as a regular expression (`regex` crate syntax),
+  case-sensitive or not
+- matches are char index ranges into the raw text, empty matches are skipped
+- in regex mode, replacements may refer to capture groups (`$1`, `${name}`), plain replacements
+  are inserted literally
+
+*/
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+pub struct Search {
+    regex: Regex,
+    options: SearchOptions,
+}
+
+impl Search {
+    pub fn new(pattern: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        let pattern = if options.regex { pattern.to_string() } else { regex::escape(pattern) };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .build()?;
+        Ok(Self { regex, options })
+    }
+
+    // char index ranges `from..to` of all matches in `text`
+    pub fn find_all(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut chars = ByteToChar::new(text);
+        self.regex.find_iter(text)
+            .filter(|m| !m.as_str().is_empty())
+            .map(|m| (chars.char_idx(m.start()), chars.char_idx(m.end())))
+            .collect()
+    }
+
+    // all matches in `text` together with the text replacing them
+    pub fn replacements(&self, text: &str, replacement: &str) -> Vec<(usize, usize, String)> {
+        let mut chars = ByteToChar::new(text);
+        self.regex.captures_iter(text)
+            .filter_map(|caps| {
+                let m = caps.get(0)?;
+                if m.as_str().is_empty() {
+                    return None;
+                }
+                let mut s = String::new();
+                if self.options.regex {
+                    caps.expand(replacement, &mut s);
+                } else {
+                    s.push_str(replacement);
+                }
+                Some((chars.char_idx(m.start()), chars.char_idx(m.end()), s))
+            })
+            .collect()
+    }
+}
+
+// converts increasing byte offsets into char indices without rescanning the text
+struct ByteToChar<'a> {
+    text: &'a str,
+    byte: usize,
+    char_idx: usize,
+}
+
+impl<'a> ByteToChar<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, byte: 0, char_idx: 0 }
+    }
+
+    fn char_idx(&mut self, byte: usize) -> usize {
+        self.char_idx += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.char_idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_and_regex_search() {
+        let plain = SearchOptions::default();
+        let search = Search::new("a.", plain).unwrap();
+        assert_eq!(search.find_all("ä a. A. ab"), vec!((2, 4), (5, 7)));
+
+        let case = SearchOptions { case_sensitive: true, ..plain };
+        assert_eq!(Search::new("a.", case).unwrap().find_all("ä a. A. ab"), vec!((2, 4)));
+
+        let regex = SearchOptions { regex: true, ..plain };
+        let search = Search::new(r"(\w)(\d)", regex).unwrap();
+        assert_eq!(search.replacements("x1 ö2", "$2$1"), vec!((0, 2, "1x".to_string()), (3, 5, "2ö".to_string())));
+        assert!(Search::new("(", regex).is_err());
+        assert_eq!(Search::new("^", regex).unwrap().find_all("a\nb"), vec!());
+    }
+}
@@ -0,0 +1,791 @@
+/*
+
+Vim mode:
+- an optional modal layer on top of the `Controller`, keys are fed in as reported by the browser
+  (`KeyboardEvent.key`) and keys with Ctrl / Alt / Meta held are left to the keymap
+- normal mode: `[count] motion`, `[count] operator [count] motion` and single key commands
+- motions: h j k l, w b e, 0 $, gg G
+- operators: d (delete), c (change), y (yank), doubled (dd, cc, yy) they work on whole lines
+- commands: i a I A o O (insert), x X D C (delete / change), p P (put), u (undo), v (visual)
+  and . (repeat the last change, including the text typed in insert mode)
+- visual mode extends the selection with the motions, it includes the char under the cursor
+  (but not the line break) and d / x / c / y act on it. . repeats d / x / c by selecting with
+  the same motions from the cursor
+- yanked and deleted text goes to a single unnamed register
+
+*/
+
+use crate::content::{Content, TextBackend};
+use crate::controller::Controller;
+use crate::words::{class, CharClass};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordStart,
+    WordEnd,
+    WordBack,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+impl Motion {
+    // motions that make operators work on whole lines
+    fn is_linewise(self) -> bool {
+        matches!(self, Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Move(Motion),
+    // `None` is the doubled operator working on `count` lines
+    Operate(Operator, Option<Motion>),
+    Command(char),
+}
+
+enum Parsed {
+    Incomplete,
+    Invalid,
+    Done(Option<usize>, Action),
+}
+
+#[derive(Default)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+pub struct Vim {
+    mode: Mode,
+    // keys of the normal mode command that's currently typed
+    pending: Vec<String>,
+    // keys of the change that's currently recorded, it ends when insert mode is left
+    recording: Option<Vec<String>>,
+    last_change: Vec<String>,
+    replaying: bool,
+    register: Register,
+    // char indices of the start of the visual selection and of the cursor in visual mode
+    visual_anchor: usize,
+    visual_head: usize,
+    // text version and selection after the last visual key, visual mode is left if either has
+    // changed outside of vim (e.g. by undo, the clipboard or the mouse)
+    visual_of: (u64, Option<(usize, usize)>),
+    // keys typed since visual mode was entered, they're the change if the selection is deleted
+    visual_keys: Vec<String>,
+}
+
+impl Default for Vim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vim {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Normal,
+            pending: vec!(),
+            recording: None,
+            last_change: vec!(),
+            replaying: false,
+            register: Register::default(),
+            visual_anchor: 0,
+            visual_head: 0,
+            visual_of: (0, None),
+            visual_keys: vec!(),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // handles a key without Ctrl / Alt / Meta, returns false if the key should be handled
+    // as usual (e.g. arrow keys, or typing in insert mode)
+    pub fn handle_key(&mut self, c: &mut Controller, key: &str) -> bool {
+        match self.mode {
+            Mode::Insert => self.insert_key(c, key),
+            Mode::Normal => self.normal_key(c, key),
+            Mode::Visual => self.visual_key(c, key),
+        }
+    }
+
+    fn insert_key(&mut self, c: &mut Controller, key: &str) -> bool {
+        match key {
+            "Escape" => {
+                self.mode = Mode::Normal;
+                let idx = c.get_idx();
+                if idx > line_start(c.get_content(), idx) {
+                    c.set_idx(idx - 1, false);
+                }
+            }
+            "Backspace" => c.key_backspace(),
+            "Delete" => c.key_delete(),
+            "Enter" => c.key_enter(),
+            _ => match single_char(key) {
+                Some(ch) => c.key_char(ch),
+                None => return false,
+            }
+        }
+        if let Some(keys) = &mut self.recording {
+            keys.push(key.to_string());
+            if self.mode == Mode::Normal {
+                self.last_change = self.recording.take().unwrap_or_default();
+            }
+        }
+        true
+    }
+
+    fn normal_key(&mut self, c: &mut Controller, key: &str) -> bool {
+        if key == "Escape" {
+            self.pending.clear();
+            return true;
+        }
+        if single_char(key).is_none() {
+            // editing keys would modify the text outside of insert mode
+            return matches!(key, "Backspace" | "Delete" | "Enter" | "Tab");
+        }
+        self.pending.push(key.to_string());
+        let (count, action) = match parse(&self.pending) {
+            Parsed::Incomplete => return true,
+            Parsed::Invalid => {
+                self.pending.clear();
+                return true;
+            }
+            Parsed::Done(count, action) => (count, action),
+        };
+        let keys = std::mem::take(&mut self.pending);
+        self.run(c, count, action);
+        if is_change(action) && !self.replaying {
+            if self.mode == Mode::Insert {
+                self.recording = Some(keys);
+            } else {
+                self.last_change = keys;
+            }
+        }
+        true
+    }
+
+    fn visual_key(&mut self, c: &mut Controller, key: &str) -> bool {
+        let selection = c.get_selection();
+        if (c.get_content().text_version(), selection) != self.visual_of {
+            self.pending.clear();
+            self.mode = Mode::Normal;
+            return self.normal_key(c, key);
+        }
+        let (from, to) = selection.unwrap_or((c.get_idx(), c.get_idx()));
+        match key {
+            "Escape" | "v" => {
+                self.pending.clear();
+                c.set_idx(self.visual_head.min(c.get_content().num_chars()), false);
+                self.mode = Mode::Normal;
+            }
+            "d" | "x" | "c" | "y" => {
+                self.pending.clear();
+                self.register = Register { text: c.get_content().get_text(from, to), linewise: false };
+                if key == "y" {
+                    c.set_idx(from, false);
+                    self.mode = Mode::Normal;
+                } else {
+                    c.replace(from, to, "");
+                    self.mode = if key == "c" { Mode::Insert } else { Mode::Normal };
+                    // repeated by selecting with the same keys from the cursor
+                    let mut keys = std::mem::take(&mut self.visual_keys);
+                    keys.push(key.to_string());
+                    if !self.replaying {
+                        if self.mode == Mode::Insert {
+                            self.recording = Some(keys);
+                        } else {
+                            self.last_change = keys;
+                        }
+                    }
+                }
+            }
+            _ if single_char(key).is_none() => return matches!(key, "Backspace" | "Delete" | "Enter" | "Tab"),
+            _ => {
+                self.pending.push(key.to_string());
+                self.visual_keys.push(key.to_string());
+                match parse(&self.pending) {
+                    Parsed::Incomplete => {}
+                    Parsed::Done(count, Action::Move(motion)) => {
+                        self.pending.clear();
+                        c.set_idx(self.visual_head.min(c.get_content().num_chars()), false);
+                        self.move_cursor(c, motion, count, false);
+                        self.visual_head = c.get_idx();
+                        self.select_visual(c);
+                    }
+                    _ => self.pending.clear(),
+                }
+            }
+        }
+        true
+    }
+
+    fn run(&mut self, c: &mut Controller, count: Option<usize>, action: Action) {
+        let n = count.unwrap_or(1);
+        let idx = c.get_idx();
+        let content = c.get_content();
+        let (start, end) = (line_start(content, idx), line_end(content, idx));
+        match action {
+            Action::Move(motion) => self.move_cursor(c, motion, count, false),
+            Action::Operate(op, motion) => self.operate(c, op, motion, count),
+            Action::Command('i') => self.mode = Mode::Insert,
+            Action::Command('a') => {
+                c.set_idx((idx + 1).min(end), false);
+                self.mode = Mode::Insert;
+            }
+            Action::Command('I') => {
+                c.set_idx(start, false);
+                self.mode = Mode::Insert;
+            }
+            Action::Command('A') => {
+                c.set_idx(end, false);
+                self.mode = Mode::Insert;
+            }
+            Action::Command('o') => {
+                c.replace(end, end, "\n");
+                self.mode = Mode::Insert;
+            }
+            Action::Command('O') => {
+                c.replace(start, start, "\n");
+                c.set_idx(start, false);
+                self.mode = Mode::Insert;
+            }
+            Action::Command('x') => self.delete(c, idx, (idx + n).min(end)),
+            Action::Command('X') => self.delete(c, idx.saturating_sub(n).max(start), idx),
+            Action::Command('D') => self.operate(c, Operator::Delete, Some(Motion::LineEnd), None),
+            Action::Command('C') => self.operate(c, Operator::Change, Some(Motion::LineEnd), None),
+            Action::Command('p') => self.put(c, true, n),
+            Action::Command('P') => self.put(c, false, n),
+            Action::Command('u') => (0..n).for_each(|_| c.undo()),
+            Action::Command('v') => {
+                self.visual_anchor = idx;
+                self.visual_head = idx;
+                self.visual_keys = vec!("v".to_string());
+                self.select_visual(c);
+                self.mode = Mode::Visual;
+            }
+            Action::Command('.') => {
+                // a count replaces the count of the change, changes without one are repeated
+                let (counted, keys) = without_counts(&self.last_change);
+                let (times, keys) = match count {
+                    Some(n) if counted => (1, std::iter::once(n.to_string()).chain(keys).collect()),
+                    Some(n) => (n, keys),
+                    None => (1, self.last_change.clone()),
+                };
+                self.replaying = true;
+                for _ in 0..times {
+                    for key in keys.iter() {
+                        self.handle_key(c, key);
+                    }
+                }
+                self.replaying = false;
+            }
+            Action::Command(_) => {}
+        }
+    }
+
+    // selects from the visual anchor to the head, including the char at the end that's
+    // further right, unless it's the end of the line
+    fn select_visual(&mut self, c: &mut Controller) {
+        let num_chars = c.get_content().num_chars();
+        self.visual_anchor = self.visual_anchor.min(num_chars);
+        self.visual_head = self.visual_head.min(num_chars);
+        let (from, to) = (self.visual_anchor.min(self.visual_head), self.visual_anchor.max(self.visual_head));
+        let to = (to + 1).min(line_end(c.get_content(), to)).max(to);
+        if self.visual_head < self.visual_anchor {
+            c.set_idx(to, false);
+            c.set_idx(from, true);
+        } else {
+            c.set_idx(from, false);
+            c.set_idx(to, true);
+        }
+        self.visual_of = (c.get_content().text_version(), c.get_selection());
+    }
+
+    fn move_cursor(&mut self, c: &mut Controller, motion: Motion, count: Option<usize>, select: bool) {
+        match motion {
+            // vertical movement keeps the pixel x position like the arrow keys
+            Motion::Up => (0..count.unwrap_or(1)).for_each(|_| c.cursor_up(select)),
+            Motion::Down => (0..count.unwrap_or(1)).for_each(|_| c.cursor_down(select)),
+            _ => {
+                let target = motion_target(c.get_content(), c.get_idx(), motion, count);
+                c.set_idx(target, select);
+            }
+        }
+    }
+
+    fn operate(&mut self, c: &mut Controller, op: Operator, motion: Option<Motion>, count: Option<usize>) {
+        let content = c.get_content();
+        let idx = c.get_idx();
+        let y = content.char_to_line(idx);
+        let n = count.unwrap_or(1);
+        let lines = match motion {
+            None => Some((y, (y + n - 1).min(content.num_lines() - 1))),
+            Some(m) if m.is_linewise() => {
+                let target = match m {
+                    Motion::Up => y.saturating_sub(n),
+                    Motion::Down => (y + n).min(content.num_lines() - 1),
+                    _ => content.char_to_line(motion_target(content, idx, m, count)),
+                };
+                Some((y.min(target), y.max(target)))
+            }
+            Some(_) => None,
+        };
+        if let Some((first, last)) = lines {
+            return self.operate_lines(c, op, first, last);
+        }
+
+        let mut motion = motion.unwrap_or(Motion::Right);
+        // like in vim, "cw" on a word changes to the end of the word only
+        if op == Operator::Change && motion == Motion::WordStart && idx < content.num_chars()
+            && class(content.char_at(idx)) != CharClass::Whitespace {
+            motion = Motion::WordEnd;
+        }
+        let mut target = motion_target(content, idx, motion, count);
+        // like in vim, "dw" on the last word of a line stops at the end of the line
+        let target_y = content.char_to_line(target);
+        if motion == Motion::WordStart && target_y > y {
+            target = (content.line_to_char(target_y - 1) + content.num_chars_of_line(target_y - 1)).max(idx);
+        }
+        let (from, mut to) = (idx.min(target), idx.max(target));
+        if motion == Motion::WordEnd {
+            to = (to + 1).min(content.num_chars());
+        }
+        self.register = Register { text: content.get_text(from, to), linewise: false };
+        match op {
+            Operator::Yank => c.set_idx(from, false),
+            Operator::Delete => c.replace(from, to, ""),
+            Operator::Change => {
+                c.replace(from, to, "");
+                self.mode = Mode::Insert;
+            }
+        }
+    }
+
+    fn operate_lines(&mut self, c: &mut Controller, op: Operator, first: usize, last: usize) {
+        let content = c.get_content();
+        let from = content.line_to_char(first);
+        let is_last = last + 1 >= content.num_lines();
+        let to = if is_last { content.num_chars() } else { content.line_to_char(last + 1) };
+        let mut text = content.get_text(from, to);
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        self.register = Register { text, linewise: true };
+        match op {
+            Operator::Yank => c.set_idx(from, false),
+            Operator::Delete => {
+                // deleting the last lines also removes the line break before them
+                let from = if is_last && first > 0 { from - 1 } else { from };
+                c.replace(from, to, "");
+                let content = c.get_content();
+                let start = content.line_to_char(first.min(content.num_lines() - 1));
+                c.set_idx(start, false);
+            }
+            Operator::Change => {
+                // the lines are emptied, but not removed
+                let end = content.line_to_char(last) + content.num_chars_of_line(last);
+                c.replace(from, end, "");
+                self.mode = Mode::Insert;
+            }
+        }
+    }
+
+    fn delete(&mut self, c: &mut Controller, from: usize, to: usize) {
+        if from < to {
+            self.register = Register { text: c.get_content().get_text(from, to), linewise: false };
+            c.replace(from, to, "");
+        }
+    }
+
+    fn put(&mut self, c: &mut Controller, after: bool, count: usize) {
+        let text = self.register.text.repeat(count);
+        if text.is_empty() {
+            return;
+        }
+        let content = c.get_content();
+        let idx = c.get_idx();
+        let y = content.char_to_line(idx);
+        if self.register.linewise {
+            if !after {
+                let start = content.line_to_char(y);
+                c.replace(start, start, &text);
+                c.set_idx(start, false);
+            } else if y + 1 < content.num_lines() {
+                let start = content.line_to_char(y + 1);
+                c.replace(start, start, &text);
+                c.set_idx(start, false);
+            } else {
+                let end = content.num_chars();
+                c.replace(end, end, &format!("\n{}", text.trim_end_matches('\n')));
+                c.set_idx(end + 1, false);
+            }
+        } else {
+            let pos = if after { (idx + 1).min(line_end(content, idx)) } else { idx };
+            c.replace(pos, pos, &text);
+            c.set_idx(pos + text.chars().count() - 1, false);
+        }
+    }
+}
+
+fn single_char(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+// `keys` of a normal mode command without its counts, and whether it had any
+fn without_counts(keys: &[String]) -> (bool, Vec<String>) {
+    let mut i = 0;
+    let count = parse_count(keys, &mut i);
+    let mut rest = keys[i..].to_vec();
+    if matches!(rest.first().map(|k| k.as_str()), Some("d") | Some("c") | Some("y")) {
+        let mut j = i + 1;
+        let motion_count = parse_count(keys, &mut j);
+        rest = std::iter::once(keys[i].clone()).chain(keys[j..].iter().cloned()).collect();
+        return (count.is_some() || motion_count.is_some(), rest);
+    }
+    (count.is_some(), rest)
+}
+
+fn is_change(action: Action) -> bool {
+    match action {
+        Action::Move(_) | Action::Operate(Operator::Yank, _) => false,
+        Action::Operate(..) => true,
+        Action::Command(c) => !matches!(c, 'u' | 'v' | '.'),
+    }
+}
+
+// parses an optional count, starting at `keys[*i]`. "0" is a motion unless it follows a digit.
+fn parse_count(keys: &[String], i: &mut usize) -> Option<usize> {
+    let mut count = None;
+    while let Some(d) = keys.get(*i).and_then(|k| single_char(k)).and_then(|c| c.to_digit(10)) {
+        if d == 0 && count.is_none() {
+            break;
+        }
+        count = Some(count.unwrap_or(0) * 10 + d as usize);
+        *i += 1;
+    }
+    count
+}
+
+// parses the motion at `keys[*i]`, `Ok(None)` if more keys are needed
+fn parse_motion(keys: &[String], i: &mut usize) -> Result<Option<Motion>, ()> {
+    let key = match keys.get(*i) {
+        Some(key) => key.as_str(),
+        None => return Ok(None),
+    };
+    *i += 1;
+    let motion = match key {
+        "h" => Motion::Left,
+        "l" => Motion::Right,
+        "k" => Motion::Up,
+        "j" => Motion::Down,
+        "w" => Motion::WordStart,
+        "e" => Motion::WordEnd,
+        "b" => Motion::WordBack,
+        "0" => Motion::LineStart,
+        "$" => Motion::LineEnd,
+        "G" => Motion::LastLine,
+        "g" => match keys.get(*i).map(|k| k.as_str()) {
+            None => return Ok(None),
+            Some("g") => {
+                *i += 1;
+                Motion::FirstLine
+            }
+            Some(_) => return Err(()),
+        },
+        _ => {
+            *i -= 1;
+            return Err(());
+        }
+    };
+    Ok(Some(motion))
+}
+
+fn parse(keys: &[String]) -> Parsed {
+    let mut i = 0;
+    let count = parse_count(keys, &mut i);
+    let key = match keys.get(i) {
+        Some(key) => key.as_str(),
+        None => return Parsed::Incomplete,
+    };
+    let op = match key {
+        "d" => Operator::Delete,
+        "c" => Operator::Change,
+        "y" => Operator::Yank,
+        _ => return match parse_motion(keys, &mut i) {
+            Ok(Some(motion)) => Parsed::Done(count, Action::Move(motion)),
+            Ok(None) => Parsed::Incomplete,
+            Err(()) if i == keys.len() - 1 && "iaIAoOxXDCpPuv.".contains(key) => {
+                Parsed::Done(count, Action::Command(single_char(key).unwrap_or(' ')))
+            }
+            Err(()) => Parsed::Invalid,
+        },
+    };
+    i += 1;
+    let motion_count = parse_count(keys, &mut i);
+    let count = match (count, motion_count) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(1) * b.unwrap_or(1)),
+    };
+    if keys.get(i).map(|k| k.as_str()) == Some(key) {
+        return Parsed::Done(count, Action::Operate(op, None));
+    }
+    match parse_motion(keys, &mut i) {
+        Ok(Some(motion)) => Parsed::Done(count, Action::Operate(op, Some(motion))),
+        Ok(None) => Parsed::Incomplete,
+        Err(()) => Parsed::Invalid,
+    }
+}
+
+fn line_start(content: &Content, idx: usize) -> usize {
+    content.line_to_char(content.char_to_line(idx))
+}
+
+fn line_end(content: &Content, idx: usize) -> usize {
+    let y = content.char_to_line(idx);
+    content.line_to_char(y) + content.num_chars_of_line(y)
+}
+
+fn is_whitespace_at(content: &Content, idx: usize) -> bool {
+    class(content.char_at(idx)) == CharClass::Whitespace
+}
+
+// char index the cursor moves to with `motion`, for all but the vertical motions
+fn motion_target(content: &Content, idx: usize, motion: Motion, count: Option<usize>) -> usize {
+    let n = count.unwrap_or(1);
+    let len = content.num_chars();
+    let mut i = idx;
+    match motion {
+        Motion::Left => idx.saturating_sub(n).max(line_start(content, idx)),
+        Motion::Right => (idx + n).min(line_end(content, idx)),
+        Motion::LineStart => line_start(content, idx),
+        Motion::LineEnd => line_end(content, idx),
+        Motion::FirstLine | Motion::LastLine => {
+            let last = content.num_lines() - 1;
+            let y = match (motion, count) {
+                (_, Some(line)) => line.max(1).min(last + 1) - 1,
+                (Motion::FirstLine, None) => 0,
+                _ => last,
+            };
+            content.line_to_char(y)
+        }
+        Motion::WordStart => {
+            for _ in 0..n {
+                if i < len && !is_whitespace_at(content, i) {
+                    let c = class(content.char_at(i));
+                    while i < len && class(content.char_at(i)) == c {
+                        i += 1;
+                    }
+                }
+                while i < len && is_whitespace_at(content, i) {
+                    i += 1;
+                }
+            }
+            i
+        }
+        Motion::WordEnd => {
+            for _ in 0..n {
+                i += 1;
+                while i < len && is_whitespace_at(content, i) {
+                    i += 1;
+                }
+                if i >= len {
+                    return len.saturating_sub(1).max(idx);
+                }
+                let c = class(content.char_at(i));
+                while i + 1 < len && class(content.char_at(i + 1)) == c {
+                    i += 1;
+                }
+            }
+            i
+        }
+        Motion::WordBack => {
+            for _ in 0..n {
+                while i > 0 && is_whitespace_at(content, i - 1) {
+                    i -= 1;
+                }
+                if i > 0 {
+                    let c = class(content.char_at(i - 1));
+                    while i > 0 && class(content.char_at(i - 1)) == c {
+                        i -= 1;
+                    }
+                }
+            }
+            i
+        }
+        Motion::Up | Motion::Down => idx,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::test_content;
+    use crate::keymap::Command;
+    use crate::measure::CharWidths;
+
+    fn setup(s: &str) -> (Vim, Controller) {
+        (Vim::new(), Controller::from_content(test_content(s, CharWidths::monospace(0.6))))
+    }
+
+    fn keys(vim: &mut Vim, c: &mut Controller, keys: &str) {
+        for key in keys.chars() {
+            let key = if key == '\u{1b}' { "Escape".to_string() } else { key.to_string() };
+            assert!(vim.handle_key(c, &key));
+        }
+    }
+
+    #[test]
+    fn motions_with_counts() {
+        let (mut vim, mut c) = setup("let snake_case = foo(1);\nsecond line\nthird");
+        keys(&mut vim, &mut c, "w");
+        assert_eq!(c.get_idx(), 4);
+        keys(&mut vim, &mut c, "2w");
+        assert_eq!(c.get_idx(), 17);
+        keys(&mut vim, &mut c, "e");
+        assert_eq!(c.get_idx(), 19);
+        keys(&mut vim, &mut c, "b$");
+        assert_eq!(c.get_idx(), 24);
+        keys(&mut vim, &mut c, "0G");
+        assert_eq!(c.get_idx(), 37);
+        keys(&mut vim, &mut c, "2gg");
+        assert_eq!(c.get_idx(), 25);
+    }
+
+    #[test]
+    fn operators_and_repeat() {
+        let (mut vim, mut c) = setup("one two three four\nfive\nsix\nseven");
+        keys(&mut vim, &mut c, "dw");
+        assert_eq!(c.get_raw_text(), "two three four\nfive\nsix\nseven");
+        keys(&mut vim, &mut c, "cwxx\u{1b}w.");
+        assert_eq!(c.get_raw_text(), "xx xx four\nfive\nsix\nseven");
+        assert_eq!(vim.mode(), Mode::Normal);
+        keys(&mut vim, &mut c, "j2dd");
+        assert_eq!(c.get_raw_text(), "xx xx four\nseven");
+        keys(&mut vim, &mut c, "ggyyGp");
+        assert_eq!(c.get_raw_text(), "xx xx four\nseven\nxx xx four");
+        keys(&mut vim, &mut c, "u");
+        assert_eq!(c.get_raw_text(), "xx xx four\nseven");
+    }
+
+    #[test]
+    fn visual_mode_deletes_selection() {
+        let (mut vim, mut c) = setup("hello world");
+        keys(&mut vim, &mut c, "v3l");
+        assert_eq!(vim.mode(), Mode::Visual);
+        assert_eq!(c.get_selection(), Some((0, 4)));
+        keys(&mut vim, &mut c, "d$P");
+        assert_eq!(c.get_raw_text(), "o worldhell");
+        assert_eq!(vim.mode(), Mode::Normal);
+
+        let (mut vim, mut c) = setup("hello world");
+        keys(&mut vim, &mut c, "vld");
+        assert_eq!(c.get_raw_text(), "llo world");
+        keys(&mut vim, &mut c, "$hvh");
+        assert_eq!(c.get_selection(), Some((7, 9)));
+        keys(&mut vim, &mut c, "lly");
+        assert_eq!(c.get_selection(), None);
+        keys(&mut vim, &mut c, "P");
+        assert_eq!(c.get_raw_text(), "llo worldd");
+    }
+
+    #[test]
+    fn visual_changes_are_repeated() {
+        let (mut vim, mut c) = setup("one two three four");
+        keys(&mut vim, &mut c, "vecX\u{1b}w.");
+        assert_eq!(c.get_raw_text(), "X X three four");
+        keys(&mut vim, &mut c, "wvld");
+        assert_eq!(c.get_raw_text(), "X X ree four");
+        keys(&mut vim, &mut c, "w.");
+        assert_eq!(c.get_raw_text(), "X X ree ur");
+        assert_eq!(vim.mode(), Mode::Normal);
+    }
+
+    #[test]
+    fn visual_mode_left_on_outside_changes() {
+        let (mut vim, mut c) = setup("hello world");
+        keys(&mut vim, &mut c, "v$");
+        assert_eq!(c.cut(), Some("hello world".to_string()));
+        keys(&mut vim, &mut c, "\u{1b}");
+        assert_eq!(vim.mode(), Mode::Normal);
+        assert_eq!(c.get_idx(), 0);
+
+        // undo removes the text the visual selection was in
+        let (mut vim, mut c) = setup("abcd");
+        keys(&mut vim, &mut c, "Aef\u{1b}vh");
+        c.execute(Command::Undo);
+        keys(&mut vim, &mut c, "l");
+        assert_eq!(vim.mode(), Mode::Normal);
+        assert_eq!(c.get_raw_text(), "abcd");
+
+        // a mouse click moves the cursor out of the selection
+        let (mut vim, mut c) = setup("abcd");
+        keys(&mut vim, &mut c, "vl");
+        c.mouse_click(0, 0);
+        keys(&mut vim, &mut c, "d");
+        assert_eq!(vim.mode(), Mode::Normal);
+        assert_eq!(c.get_raw_text(), "abcd");
+    }
+
+    #[test]
+    fn dw_stops_at_line_end() {
+        let (mut vim, mut c) = setup("ab\ncd");
+        keys(&mut vim, &mut c, "dw");
+        assert_eq!(c.get_raw_text(), "\ncd");
+        let (mut vim, mut c) = setup("ab cd\n  ef");
+        keys(&mut vim, &mut c, "wcwx\u{1b}");
+        assert_eq!(c.get_raw_text(), "ab x\n  ef");
+    }
+
+    #[test]
+    fn repeat_with_count() {
+        let (mut vim, mut c) = setup("abcdefgh");
+        keys(&mut vim, &mut c, "x3.");
+        assert_eq!(c.get_raw_text(), "efgh");
+        keys(&mut vim, &mut c, "2x.");
+        assert_eq!(c.get_raw_text(), "");
+        let (mut vim, mut c) = setup("a b c d e f");
+        keys(&mut vim, &mut c, "d2w3.");
+        assert_eq!(c.get_raw_text(), "f");
+        let (mut vim, mut c) = setup("");
+        keys(&mut vim, &mut c, "ia\u{1b}2.");
+        assert_eq!(c.get_raw_text(), "aaa");
+    }
+}
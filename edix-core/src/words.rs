@@ -0,0 +1,128 @@
+/*
+
+Word boundaries:
+- chars are classified as whitespace, word chars (alphanumeric and '_') or punctuation
+- there's a boundary wherever the class changes, so "snake_case" is a single word
+- token boundaries from syntax highlighting are used as additional boundaries between two
+  non-whitespace chars of the same class (e.g. the numeric literal and its suffix in "1u8")
+- whitespace belongs to the following word when moving right and to the previous word when
+  moving left, so word movement skips over it
+- the word at a position (e.g. for double-clicks) is the run between boundaries around the char
+  after it, or the char before it if only that one is a word char
+
+*/
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+pub(crate) fn class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+// whether there's a boundary between `chars[i-1]` and `chars[i]`. `token_starts` is sorted.
+fn is_boundary(chars: &[char], token_starts: &[usize], i: usize) -> bool {
+    if i == 0 || i >= chars.len() {
+        return true;
+    }
+    let (a, b) = (class(chars[i-1]), class(chars[i]));
+    a != b || (a != CharClass::Whitespace && token_starts.binary_search(&i).is_ok())
+}
+
+// index of the end of the word at or after `x`
+pub fn next_word_end(chars: &[char], token_starts: &[usize], x: usize) -> usize {
+    let mut i = x;
+    while i < chars.len() && class(chars[i]) == CharClass::Whitespace {
+        i += 1;
+    }
+    if i < chars.len() {
+        i += 1;
+        while !is_boundary(chars, token_starts, i) {
+            i += 1;
+        }
+    }
+    i
+}
+
+// index of the start of the word before `x`
+pub fn prev_word_start(chars: &[char], token_starts: &[usize], x: usize) -> usize {
+    let mut i = x;
+    while i > 0 && class(chars[i-1]) == CharClass::Whitespace {
+        i -= 1;
+    }
+    if i > 0 {
+        i -= 1;
+        while !is_boundary(chars, token_starts, i) {
+            i -= 1;
+        }
+    }
+    i
+}
+
+// range `from..to` of the word (or run of whitespace or punctuation) at `x`
+pub fn word_around(chars: &[char], token_starts: &[usize], x: usize) -> (usize, usize) {
+    if chars.is_empty() {
+        return (0, 0);
+    }
+    let is_word = |i: usize| class(chars[i]) == CharClass::Word;
+    let i = if x == chars.len() || (x > 0 && is_word(x - 1) && !is_word(x)) { x - 1 } else { x };
+    let mut from = i;
+    while !is_boundary(chars, token_starts, from) {
+        from -= 1;
+    }
+    let mut to = i + 1;
+    while !is_boundary(chars, token_starts, to) {
+        to += 1;
+    }
+    (from, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn moves_over_words_and_punctuation() {
+        let line = chars("let snake_case = foo(1);");
+        let ends: Vec<usize> = std::iter::successors(Some(0), |&x| {
+            Some(next_word_end(&line, &[], x)).filter(|&next| next != x)
+        }).collect();
+        assert_eq!(ends, vec!(0, 3, 14, 16, 20, 21, 22, 24));
+        assert_eq!(prev_word_start(&line, &[], 24), 22);
+        assert_eq!(prev_word_start(&line, &[], 16), 15);
+        assert_eq!(prev_word_start(&line, &[], 14), 4);
+    }
+
+    #[test]
+    fn token_starts_split_words() {
+        let line = chars("x = 1u8;");
+        assert_eq!(next_word_end(&line, &[], 3), 7);
+        assert_eq!(next_word_end(&line, &[0, 1, 2, 3, 4, 5, 7], 3), 5);
+        assert_eq!(prev_word_start(&line, &[0, 1, 2, 3, 4, 5, 7], 7), 5);
+    }
+
+    #[test]
+    fn word_around_position() {
+        let line = chars("x = 1u8;  foo");
+        let tokens = [0, 1, 2, 3, 4, 5, 7, 8, 10];
+        assert_eq!(word_around(&line, &tokens, 4), (4, 5));
+        assert_eq!(word_around(&line, &[], 4), (4, 7));
+        assert_eq!(word_around(&line, &tokens, 7), (5, 7));
+        assert_eq!(word_around(&line, &tokens, 9), (8, 10));
+        assert_eq!(word_around(&line, &tokens, 13), (10, 13));
+        assert_eq!(word_around(&[], &[], 0), (0, 0));
+    }
+}
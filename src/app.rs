@@ -1,11 +1,22 @@
 use yew::prelude::*;
-use crate::controller::Controller;
-use crate::content::RichContentOptions;
+use edix_core::controller::Controller;
+use edix_core::content::RichContentOptions;
+use edix_core::font_rules::{default_font_rules, FontRule, FontRules};
+use edix_core::fonts::{default_font_profiles, FontProfile};
+use edix_core::highlight::to_html_color;
+use edix_core::indent::IndentUnit;
+use edix_core::keymap::{Command, KeyChord, Keymap};
+use edix_core::search::{Search, SearchOptions};
+use edix_core::vim::Vim;
+use crate::canvas_measure::CanvasMeasure;
+use crate::render::{content_to_html, matches_to_html, visible_lines};
 use syntect::parsing::SyntaxSet;
 use syntect::highlighting::ThemeSet;
 use yew::services::storage::{Area, StorageService};
 use yew::format::Json;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::JsCast;
 
 const KEY: &str = "edix1.self";
 const DEFAULT_TEXT: &str = include_str!("../default-text.txt");
@@ -14,23 +25,47 @@ pub struct App {
     link: ComponentLink<Self>,
     storage: StorageService,
     controller: Controller,
+    keymap: Keymap,
+    // `Some` while vim mode is enabled
+    vim: Option<Vim>,
+    search_pattern: String,
+    replacement: String,
+    search_options: SearchOptions,
+    // matches of the search, and the pattern, options and `Content::text_version` they're for
+    matches: Vec<(usize, usize)>,
+    matches_of: Option<(String, SearchOptions, u64)>,
+    editor_ref: NodeRef,
+    find_ref: NodeRef,
+    // the scrolled viewport of the editor in px, only the lines in (and around) it are rendered
+    scroll_ref: NodeRef,
+    scroll_top: usize,
+    viewport_height: usize,
+    // `scroll_top` was changed to follow the cursor and has to be applied after rendering
+    scroll_pending: bool,
+    // `Some` while the mouse button is held down after clicking into the editor
+    drag: Option<Drag>,
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub enum FontSelection {
-    Monospace,
-    Sans,
-    Mixed,
-}
-
 #[derive(Serialize, Deserialize)]
 pub struct State {
     pub content:String,
     pub selected_theme: String,
     pub selected_syntax: String,
-    pub font_selection: FontSelection,
+    #[serde(alias = "font_selection")]
+    pub selected_font_profile: String,
+    #[serde(default = "default_font_profiles")]
+    pub font_profiles: Vec<FontProfile>,
+    #[serde(default = "default_font_rules")]
+    pub font_rules: Vec<FontRule>,
+    // overrides of the default key bindings, `None` unbinds a chord
+    #[serde(default)]
+    pub key_bindings: BTreeMap<String, Option<Command>>,
+    #[serde(default)]
+    pub vim_mode: bool,
+    #[serde(default)]
+    pub indent_unit: IndentUnit,
 }
 
 impl Default for State {
@@ -39,18 +74,61 @@ impl Default for State {
             content: DEFAULT_TEXT.to_string(),
             selected_theme: "InspiredGitHub".to_string(),
             selected_syntax: "Python".to_string(),
-            font_selection: FontSelection::Mixed,
+            selected_font_profile: "Mixed".to_string(),
+            font_profiles: default_font_profiles(),
+            font_rules: default_font_rules(),
+            key_bindings: BTreeMap::new(),
+            vim_mode: false,
+            indent_unit: IndentUnit::default(),
+        }
+    }
+}
+
+impl State {
+    fn from_controller(controller: &Controller, keymap: &Keymap, vim_mode: bool) -> State {
+        let options = controller.get_options();
+        State {
+            content: controller.get_raw_text(),
+            selected_theme: options.selected_theme.clone(),
+            selected_syntax: options.selected_syntax.clone(),
+            selected_font_profile: options.selected_font_profile.clone(),
+            font_profiles: options.font_profiles.clone(),
+            font_rules: options.font_rules.get_rules().to_vec(),
+            key_bindings: keymap.get_overrides().clone(),
+            vim_mode,
+            indent_unit: options.indent_unit,
         }
     }
 }
 
+enum Drag {
+    // extends the selection from the clicked position
+    Select,
+    // box selection from the clicked pixel position (Alt+drag)
+    Box(usize, usize),
+}
 
 pub enum Msg {
     KeyDown(yew::events::KeyboardEvent),
     MouseClick(yew::events::MouseEvent),
+    MouseMove(yew::events::MouseEvent),
+    MouseUp,
+    Scroll,
+    Copy(web_sys::Event),
+    Cut(web_sys::Event),
+    Paste(web_sys::Event),
     ThemeChange(ChangeData),
     SyntaxChange(ChangeData),
     FontChange(ChangeData),
+    IndentChange(ChangeData),
+    VimToggle,
+    FindInput(String),
+    ReplaceInput(String),
+    FindKeyDown(yew::events::KeyboardEvent),
+    RegexToggle,
+    CaseToggle,
+    ReplaceNext,
+    ReplaceAll,
     Reset,
 }
 
@@ -72,6 +150,9 @@ impl Component for App {
                 if !theme_set.themes.contains_key(&restored_model.selected_theme) {
                     restored_model.selected_theme = theme_set.themes.keys().next().unwrap().to_string();
                 }
+                if restored_model.font_profiles.is_empty() {
+                    restored_model.font_profiles = default_font_profiles();
+                }
                 restored_model
             } else {
                 State::default()
@@ -82,57 +163,134 @@ impl Component for App {
             line_height: 20,
             selected_syntax: state.selected_syntax,
             selected_theme: state.selected_theme,
-            font_selection: state.font_selection
+            selected_font_profile: state.selected_font_profile,
+            font_profiles: state.font_profiles,
+            font_rules: FontRules::new(state.font_rules).unwrap_or_default(),
+            indent_unit: state.indent_unit,
         };
-        let controller = Controller::new(&state.content, options);
+        let controller = Controller::new(&state.content, options, Box::new(CanvasMeasure));
+        let keymap = Keymap::new(state.key_bindings);
+        let vim = if state.vim_mode { Some(Vim::new()) } else { None };
 
-        let app = App {
+        App {
             link,
             controller,
+            keymap,
+            vim,
+            search_pattern: String::new(),
+            replacement: String::new(),
+            search_options: SearchOptions::default(),
+            matches: vec!(),
+            matches_of: None,
+            editor_ref: NodeRef::default(),
+            find_ref: NodeRef::default(),
+            scroll_ref: NodeRef::default(),
+            scroll_top: 0,
+            viewport_height: 0,
+            scroll_pending: false,
+            drag: None,
             storage,
             syntax_set,
             theme_set
-        };
-
-        app
+        }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        // messages that may move the cursor out of view
+        let follow_cursor = matches!(msg, Msg::KeyDown(_) | Msg::Cut(_) | Msg::Paste(_) | Msg::FindKeyDown(_) | Msg::ReplaceNext);
+        // the stored state only changes with the text or the settings
+        let text_version = self.controller.get_content().text_version();
+        let settings_changed = matches!(msg, Msg::ThemeChange(_) | Msg::SyntaxChange(_) | Msg::FontChange(_)
+            | Msg::IndentChange(_) | Msg::VimToggle | Msg::Reset);
         match msg {
             Msg::KeyDown(e) => {
                 //yew::services::ConsoleService::log(&format!("key: {}, alt: {}, shift: {}, meta: {}, ctrl: {}", e.key(), e.alt_key(), e.shift_key(), e.meta_key(), e.ctrl_key()));
-                if e.alt_key() || e.ctrl_key() || e.meta_key() {
-                    // ignore
-                } else {
-                    // HACK: prevent_default should only be called for known and handeled keys
-                    loop {
-                        match e.key().as_ref() {
-                            "ArrowUp" => self.controller.cursor_up(),
-                            "ArrowDown" => self.controller.cursor_down(),
-                            "ArrowRight" => self.controller.cursor_right(),
-                            "ArrowLeft" => self.controller.cursor_left(),
-                            "Home" => self.controller.cursor_home(),
-                            "End" => self.controller.cursor_end(),
-                            "Backspace" => self.controller.key_backspace(),
-                            "Enter" => self.controller.key_enter(),
-                            "Delete" => self.controller.key_delete(),
-                            c if c.chars().count() == 1 => self.controller.key_char(c.chars().next().unwrap()),
-                            _ => { break; }
-                        }
-                        e.prevent_default();
-                        break;
+                let plain = !(e.ctrl_key() || e.alt_key() || e.meta_key());
+                let chord = KeyChord::new(&e.key(), e.ctrl_key(), e.alt_key(), e.shift_key(), e.meta_key());
+                let vim_handled = match &mut self.vim {
+                    Some(vim) if plain => vim.handle_key(&mut self.controller, &e.key()),
+                    _ => false,
+                };
+                if vim_handled {
+                    e.prevent_default();
+                } else if let Some(command) = self.keymap.get(&chord) {
+                    match command {
+                        Command::Find => focus(&self.find_ref),
+                        _ => self.controller.execute(command),
                     }
+                    e.prevent_default();
+                } else if plain && e.key().chars().count() == 1 {
+                    self.controller.key_char(e.key().chars().next().unwrap());
+                    e.prevent_default();
                 }
+                // unbound chords (e.g. cut/copy/paste) are left to the browser
             }
             Msg::MouseClick(e) => {
-                let elmt = yew::utils::document().query_selector("#ed-view").unwrap().unwrap();
-                let dims = elmt.get_bounding_client_rect();
-                let (x, y) = (e.client_x() - dims.x() as i32, e.client_y() - dims.y() as i32);
-                if x >= 0 && y >= 0 {
-                    self.controller.mouse_click(x as usize, y as usize);
+                if let Some((x, y)) = mouse_pos(&e) {
+                    // `detail` counts the clicks of double- and triple-clicks
+                    self.drag = None;
+                    if e.alt_key() {
+                        self.controller.add_cursor(x, y);
+                        self.drag = Some(Drag::Box(x, y));
+                    } else if e.shift_key() {
+                        self.controller.mouse_select(x, y);
+                        self.drag = Some(Drag::Select);
+                    } else if e.detail() == 2 {
+                        self.controller.mouse_double_click(x, y);
+                    } else if e.detail() >= 3 {
+                        self.controller.mouse_triple_click(x, y);
+                    } else {
+                        self.controller.mouse_click(x, y);
+                        self.drag = Some(Drag::Select);
+                    }
                 }
                 //e.prevent_default();
             }
+            Msg::MouseMove(e) => {
+                // only dragging with the primary button down changes anything
+                match (&self.drag, mouse_pos(&e)) {
+                    (Some(Drag::Select), Some((x, y))) if e.buttons() & 1 == 1 => {
+                        self.controller.mouse_select(x, y);
+                    }
+                    (Some(Drag::Box(ax, ay)), Some(pos)) if e.buttons() & 1 == 1 => {
+                        if pos != (*ax, *ay) {
+                            self.controller.box_select((*ax, *ay), pos);
+                        }
+                    }
+                    _ => return false,
+                }
+            }
+            Msg::MouseUp => {
+                self.drag = None;
+                return false;
+            }
+            Msg::Scroll => {
+                let visible = self.visible_lines();
+                if let Some(elmt) = self.scroll_ref.cast::<web_sys::Element>() {
+                    self.scroll_top = elmt.scroll_top().max(0) as usize;
+                    self.viewport_height = elmt.client_height().max(0) as usize;
+                }
+                // only re-render when other lines come into view
+                return self.visible_lines() != visible;
+            }
+            Msg::Copy(e) => {
+                if let Some(text) = self.controller.get_selected_text() {
+                    set_clipboard_text(&e, &text);
+                }
+            }
+            Msg::Cut(e) => {
+                if let Some(text) = self.controller.cut() {
+                    set_clipboard_text(&e, &text);
+                }
+            }
+            Msg::Paste(e) => {
+                if let Some(data) = clipboard_data(&e) {
+                    if let Ok(text) = data.get_data("text/plain") {
+                        self.controller.paste(&text);
+                    }
+                }
+                e.prevent_default();
+            }
             Msg::ThemeChange(cd) => {
                 if let ChangeData::Select(elmt) = cd {
                     self.controller.set_theme(elmt.value());
@@ -145,12 +303,48 @@ impl Component for App {
             }
             Msg::FontChange(cd) => {
                 if let ChangeData::Select(elmt) = cd {
-                    self.controller.set_font(match elmt.value().as_ref() {
-                        "Monospace" => FontSelection::Monospace,
-                        "Sans" => FontSelection::Sans,
-                        "Mixed" => FontSelection::Mixed,
-                        _ => unreachable!()
-                    });
+                    self.controller.set_font_profile(elmt.value());
+                }
+            }
+            Msg::IndentChange(cd) => {
+                if let ChangeData::Select(elmt) = cd {
+                    let indent_unit = match elmt.value().as_str() {
+                        "tab" => IndentUnit { use_tabs: true, ..self.controller.get_options().indent_unit },
+                        width => IndentUnit { use_tabs: false, width: width.parse().unwrap_or(4) },
+                    };
+                    self.controller.set_indent_unit(indent_unit);
+                }
+            }
+            Msg::VimToggle => {
+                self.vim = match self.vim {
+                    Some(_) => None,
+                    None => Some(Vim::new()),
+                };
+            }
+            Msg::FindInput(pattern) => self.search_pattern = pattern,
+            Msg::ReplaceInput(replacement) => self.replacement = replacement,
+            Msg::FindKeyDown(e) => {
+                match e.key().as_str() {
+                    "Enter" => {
+                        if let Some(Ok(search)) = self.search() {
+                            self.controller.find_next(&search, e.shift_key());
+                        }
+                        e.prevent_default();
+                    }
+                    "Escape" => focus(&self.editor_ref),
+                    _ => return false,
+                }
+            }
+            Msg::RegexToggle => self.search_options.regex = !self.search_options.regex,
+            Msg::CaseToggle => self.search_options.case_sensitive = !self.search_options.case_sensitive,
+            Msg::ReplaceNext => {
+                if let Some(Ok(search)) = self.search() {
+                    self.controller.replace_next(&search, &self.replacement);
+                }
+            }
+            Msg::ReplaceAll => {
+                if let Some(Ok(search)) = self.search() {
+                    self.controller.replace_all(&search, &self.replacement);
                 }
             }
             Msg::Reset => {
@@ -159,21 +353,52 @@ impl Component for App {
                     line_height: 20,
                     selected_syntax: state.selected_syntax,
                     selected_theme: state.selected_theme,
-                    font_selection: state.font_selection
+                    selected_font_profile: state.selected_font_profile,
+                    font_profiles: state.font_profiles,
+                    font_rules: FontRules::new(state.font_rules).unwrap_or_default(),
+                    indent_unit: state.indent_unit,
                 };
-                let controller = Controller::new(&state.content, options);
+                let controller = Controller::new(&state.content, options, Box::new(CanvasMeasure));
                 self.controller = controller;
+                self.keymap = Keymap::new(state.key_bindings);
+                self.vim = None;
+                // the new content's text versions start over
+                self.matches_of = None;
             }
         }
-        self.storage.store(KEY, Json(&self.controller.get_state()));
+        self.update_matches();
+        if follow_cursor {
+            self.scroll_to_cursor();
+        }
+        if settings_changed || self.controller.get_content().text_version() != text_version {
+            self.storage.store(KEY, Json(&State::from_controller(&self.controller, &self.keymap, self.vim.is_some())));
+        }
         true
     }
 
+    fn rendered(&mut self, first_render: bool) {
+        if first_render {
+            // the viewport's height is only known once it's in the DOM
+            self.link.send_message(Msg::Scroll);
+        } else if self.scroll_pending {
+            self.scroll_pending = false;
+            if let Some(elmt) = self.scroll_ref.cast::<web_sys::Element>() {
+                elmt.set_scroll_top(self.scroll_top as i32);
+            }
+        }
+    }
+
     fn view(&self) -> Html {
         let options = self.controller.get_options();
-        let bg_color = self.theme_set.themes[&options.selected_theme].settings.background.as_ref().map(crate::highlight::to_html_color).unwrap_or("#0000".to_string());
-        let fg_color = self.theme_set.themes[&options.selected_theme].settings.foreground.as_ref().map(crate::highlight::to_html_color).unwrap_or("#ffff".to_string());
-        let cur_style = format!("top: {}px; left: {}px; background-color: {};", self.controller.get_y(), self.controller.get_x(), fg_color);
+        let matches = &self.matches;
+        let search_info = match self.search() {
+            Some(Ok(_)) => format!("{} matches", matches.len()),
+            Some(Err(e)) => e,
+            None => String::new(),
+        };
+        let visible = self.visible_lines();
+        let bg_color = self.theme_set.themes[&options.selected_theme].settings.background.as_ref().map(to_html_color).unwrap_or("#0000".to_string());
+        let fg_color = self.theme_set.themes[&options.selected_theme].settings.foreground.as_ref().map(to_html_color).unwrap_or("#ffff".to_string());
         html! {
             <>
             <div style="padding: 10px;">
@@ -188,22 +413,64 @@ impl Component for App {
                     )}
                 </select>
                 <span>{"Font style: "}</span><select onchange=self.link.callback(|e| Msg::FontChange(e))>
-                    <option value="Monospace" selected=options.font_selection==FontSelection::Monospace>{"Monospace"}</option>
-                    <option value="Sans" selected=options.font_selection==FontSelection::Sans>{"Sans"}</option>
-                    <option value="Mixed" selected=options.font_selection==FontSelection::Mixed>{"Mixed"}</option>
+                    {for options.font_profiles.iter().map(
+                        |p| html!(<option value=p.name selected=options.font_profile().name==p.name>{&p.name}</option>)
+                    )}
                 </select>
+                <span>{"Indent: "}</span><select onchange=self.link.callback(|e| Msg::IndentChange(e))>
+                    <option value="tab" selected=options.indent_unit.use_tabs>{"Tab"}</option>
+                    {for [2, 4, 8].iter().map(|w| html!(
+                        <option value=w.to_string() selected=!options.indent_unit.use_tabs && options.indent_unit.width == *w>
+                            {format!("{} spaces", w)}
+                        </option>
+                    ))}
+                </select>
+                <label>
+                    <input type="checkbox" checked=self.vim.is_some() onclick=self.link.callback(|_| Msg::VimToggle)/>
+                    {"Vim mode"}
+                </label>
+                {for self.vim.as_ref().map(|vim| html!(<span class="ed-mode">{format!("-- {} --", vim.mode())}</span>))}
                 <button onclick=self.link.callback(|_| Msg::Reset)>{"Reset"}</button>
             </div>
+            <div class="ed-find">
+                <input type="text" placeholder="Find" ref=self.find_ref.clone() value=&self.search_pattern
+                    oninput=self.link.callback(|e: InputData| Msg::FindInput(e.value))
+                    onkeydown=self.link.callback(|e| Msg::FindKeyDown(e))/>
+                <input type="text" placeholder="Replace" value=&self.replacement
+                    oninput=self.link.callback(|e: InputData| Msg::ReplaceInput(e.value))/>
+                <label>
+                    <input type="checkbox" checked=self.search_options.regex onclick=self.link.callback(|_| Msg::RegexToggle)/>
+                    {"Regex"}
+                </label>
+                <label>
+                    <input type="checkbox" checked=self.search_options.case_sensitive onclick=self.link.callback(|_| Msg::CaseToggle)/>
+                    {"Match case"}
+                </label>
+                <button onclick=self.link.callback(|_| Msg::ReplaceNext)>{"Replace"}</button>
+                <button onclick=self.link.callback(|_| Msg::ReplaceAll)>{"Replace all"}</button>
+                <span>{search_info}</span>
+            </div>
             <div style="padding: 10px;">
+                <div id="ed-scroll" ref=self.scroll_ref.clone() onscroll=self.link.callback(|_| Msg::Scroll)>
                 <div 
                     id="ed-view"
+                    ref=self.editor_ref.clone()
                     tabindex="0" 
                     onkeydown=self.link.callback(|e| Msg::KeyDown(e)) 
                     onmousedown=self.link.callback(|e| Msg::MouseClick(e))
+                    onmousemove=self.link.callback(|e| Msg::MouseMove(e))
+                    onmouseup=self.link.callback(|_| Msg::MouseUp)
+                    oncopy=self.link.callback(|e| Msg::Copy(e))
+                    oncut=self.link.callback(|e| Msg::Cut(e))
+                    onpaste=self.link.callback(|e| Msg::Paste(e))
                     style={format!("position:relative; background-color: {}; color: {};", bg_color, fg_color)}
                 >
-                    <span class="ed-cursor" style={cur_style}></span>
-                    {self.controller.get_html()}
+                    {for self.controller.get_carets().into_iter().map(|(x, y)| html!(
+                        <span class="ed-cursor" style={format!("top: {}px; left: {}px; background-color: {};", y, x, fg_color)}></span>
+                    ))}
+                    {content_to_html(self.controller.get_content(), &self.controller.get_selections(), visible)}
+                    {matches_to_html(self.controller.get_content(), matches, visible)}
+                </div>
                 </div>
             </div>
             </>
@@ -214,3 +481,72 @@ impl Component for App {
         false
     }
 }
+
+impl App {
+    // the search entered in the find bar, `None` if it's empty
+    fn search(&self) -> Option<Result<Search, String>> {
+        if self.search_pattern.is_empty() {
+            return None;
+        }
+        Some(Search::new(&self.search_pattern, self.search_options).map_err(|e| e.to_string()))
+    }
+
+    // searches the text again if it or the search changed since the last search
+    fn update_matches(&mut self) {
+        let of = (self.search_pattern.clone(), self.search_options, self.controller.get_content().text_version());
+        if self.matches_of.as_ref() == Some(&of) {
+            return;
+        }
+        self.matches = match self.search() {
+            Some(Ok(search)) => self.controller.find_matches(&search),
+            _ => vec!(),
+        };
+        self.matches_of = Some(of);
+    }
+
+    fn visible_lines(&self) -> (usize, usize) {
+        visible_lines(self.controller.get_content(), self.scroll_top, self.viewport_height)
+    }
+
+    // scrolls the viewport just far enough for the cursor's line to be in view
+    fn scroll_to_cursor(&mut self) {
+        let line_height = self.controller.get_options().line_height;
+        let y = self.controller.get_y();
+        if y < self.scroll_top {
+            self.scroll_top = y;
+        } else if y + line_height > self.scroll_top + self.viewport_height {
+            self.scroll_top = (y + line_height).saturating_sub(self.viewport_height);
+        } else {
+            return;
+        }
+        self.scroll_pending = true;
+    }
+}
+
+// position of the mouse relative to the editor view, `None` if it's outside
+fn mouse_pos(e: &yew::events::MouseEvent) -> Option<(usize, usize)> {
+    let elmt = yew::utils::document().query_selector("#ed-view").unwrap().unwrap();
+    let dims = elmt.get_bounding_client_rect();
+    let (x, y) = (e.client_x() - dims.x() as i32, e.client_y() - dims.y() as i32);
+    if x >= 0 && y >= 0 { Some((x as usize, y as usize)) } else { None }
+}
+
+fn focus(node: &NodeRef) {
+    if let Some(elmt) = node.cast::<web_sys::HtmlElement>() {
+        let _ = elmt.focus();
+    }
+}
+
+// `clipboardData` of a cut / copy / paste event. it's looked up dynamically, as `ClipboardEvent`
+// is an unstable API in the web-sys versions that work with yew 0.17.
+fn clipboard_data(e: &web_sys::Event) -> Option<web_sys::DataTransfer> {
+    js_sys::Reflect::get(e, &"clipboardData".into()).ok()?.dyn_into().ok()
+}
+
+fn set_clipboard_text(e: &web_sys::Event, text: &str) {
+    if let Some(data) = clipboard_data(e) {
+        if data.set_data("text/plain", text).is_ok() {
+            e.prevent_default();
+        }
+    }
+}
@@ -0,0 +1,72 @@
+/*
+
+Text measurement in the browser:
+- uses `measureText` of an offscreen canvas, which doesn't trigger a layout
+- the canvas context is created once and reused for all measurements
+- widths are cached by font and text, as `Content` measures every prefix of a line's runs and
+  re-measures a line whenever it changes. the cache is only filled once the web fonts are loaded
+
+*/
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use edix_core::fonts::Font;
+use edix_core::measure::Measure;
+use syntect::highlighting::FontStyle;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, FontFaceSetLoadStatus, HtmlCanvasElement};
+
+pub struct CanvasMeasure;
+
+thread_local!(
+    static CONTEXT: RefCell<Option<CanvasRenderingContext2d>> = const { RefCell::new(None) };
+    // widths by font shorthand and text
+    static WIDTHS: RefCell<HashMap<(String, String), f64>> = RefCell::new(HashMap::new());
+);
+
+// number of cached widths at which the cache is cleared
+const MAX_CACHED: usize = 100_000;
+
+impl Measure for CanvasMeasure {
+    fn width(&self, font: &Font, font_style: FontStyle, text: &str) -> f64 {
+        let shorthand = format!(
+            "{} {} {}px {}",
+            if font_style.contains(FontStyle::ITALIC) { "italic" } else { "normal" },
+            if font_style.contains(FontStyle::BOLD) { "bold".to_string() } else { font.weight.to_string() },
+            font.size,
+            font.family
+        );
+        let key = (shorthand, text.to_string());
+        if let Some(width) = WIDTHS.with(|w| w.borrow().get(&key).copied()) {
+            return width;
+        }
+        let width = CONTEXT.with(|ctx| {
+            let mut ctx = ctx.borrow_mut();
+            let ctx = ctx.get_or_insert_with(create_context);
+            ctx.set_font(&key.0);
+            ctx.measure_text(text).map(|m| m.width()).unwrap_or(0.0)
+        });
+        if self.is_ready() {
+            WIDTHS.with(|w| {
+                let mut w = w.borrow_mut();
+                if w.len() >= MAX_CACHED {
+                    w.clear();
+                }
+                w.insert(key, width);
+            });
+        }
+        width
+    }
+
+    // measurements taken while web fonts are still loading use fallback fonts
+    fn is_ready(&self) -> bool {
+        let document = web_sys::window().unwrap().document().unwrap();
+        document.fonts().status() == FontFaceSetLoadStatus::Loaded
+    }
+}
+
+fn create_context() -> CanvasRenderingContext2d {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let canvas: HtmlCanvasElement = document.create_element("canvas").unwrap().dyn_into().unwrap();
+    canvas.get_context("2d").unwrap().unwrap().dyn_into().unwrap()
+}
@@ -1,9 +1,7 @@
 #![recursion_limit="2048"]
 mod app;
-pub mod cursor;
-pub mod content;
-pub mod controller;
-pub mod highlight;
+mod canvas_measure;
+mod render;
 
 use wasm_bindgen::prelude::*;
 
@@ -0,0 +1,235 @@
+use edix_core::content::{split_regions, Content, Region, TextBackend};
+use edix_core::highlight::{find_highlight_color, selection_color};
+use std::rc::Rc;
+use yew::prelude::*;
+
+// lines rendered above and below the visible ones, so that short scrolls don't show gaps
+const OVERSCAN: usize = 20;
+
+// range `first..last` of the lines to render for the viewport `scroll_top..scroll_top + height` (px)
+pub fn visible_lines(content: &Content, scroll_top: usize, height: usize) -> (usize, usize) {
+    let line_height = content.line_height();
+    let first = (scroll_top / line_height).saturating_sub(OVERSCAN);
+    let last = (scroll_top + height) / line_height + 1 + OVERSCAN;
+    (first.min(content.num_lines()), last.min(content.num_lines()))
+}
+
+// renders the lines `first..last`, the others are replaced by spacers of the same height.
+// `selections` are the char index ranges `from..to` that are rendered as selected
+pub fn content_to_html(content: &Content, selections: &[(usize, usize)], (first, last): (usize, usize)) -> Html {
+    let sel_sty: Rc<str> = format!("background-color: {};", selection_color(&content.options.selected_theme)).into();
+    let line_height = content.line_height();
+    let spacer = |lines: usize| html!(<div style={format!("height: {}px;", lines * line_height)}></div>);
+    let mut line_start = content.line_to_char(first);
+    html!(<>
+        {spacer(first)}
+        {for (first..last).map(|y| {
+            let props = line_props(content, y, line_start, selections, &sel_sty);
+            line_start += content.num_chars_of_line(y) + 1;
+            // lines are keyed by their version, so unchanged lines keep their component when
+            // lines are inserted or removed above them
+            html!(
+                <LineView
+                    key=props.version.to_string()
+                    version=props.version
+                    regions=props.regions
+                    tab_widths=props.tab_widths
+                    sels=props.sels
+                    sel_sty=props.sel_sty
+                />
+            )
+        })}
+        {spacer(content.num_lines() - last)}
+    </>)
+}
+
+// properties of line `y`, which starts at char index `line_start`
+fn line_props(content: &Content, y: usize, line_start: usize, selections: &[(usize, usize)], sel_sty: &Rc<str>) -> LineProps {
+    let line_len = content.num_chars_of_line(y);
+    let regions = content.get_lines()[y].clone();
+    LineProps {
+        version: content.line_version(y),
+        tab_widths: tab_widths(content, y, &regions),
+        regions,
+        // selections relative to this line, newline included
+        sels: selections.iter()
+            .filter(|&&(from, to)| to > line_start && from <= line_start + line_len)
+            .map(|&(from, to)| (from.saturating_sub(line_start), to - line_start))
+            .collect(),
+        sel_sty: sel_sty.clone(),
+    }
+}
+
+#[derive(Clone, Properties)]
+pub struct LineProps {
+    // `Content::line_version` of the line, the regions only change together with it
+    pub version: u64,
+    pub regions: Rc<Vec<Region>>,
+    // widths of the line's tabs in px, in order
+    pub tab_widths: Vec<usize>,
+    // selections relative to the line, `to` is past the line's length if the newline is selected
+    pub sels: Vec<(usize, usize)>,
+    pub sel_sty: Rc<str>,
+}
+
+// a single line, only re-rendered if it has changed since its last render
+pub struct LineView {
+    props: LineProps,
+}
+
+impl Component for LineView {
+    type Message = ();
+    type Properties = LineProps;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let old = &self.props;
+        let changed = props.version != old.version
+            || props.tab_widths != old.tab_widths
+            || props.sels != old.sels
+            || props.sel_sty != old.sel_sty;
+        self.props = props;
+        changed
+    }
+
+    fn view(&self) -> Html {
+        let LineProps { regions, tab_widths, sels, sel_sty, .. } = &self.props;
+        let line_len: usize = regions.iter().map(|r| r.text().chars().count()).sum();
+        let mut tab_widths = tab_widths.iter().copied();
+        html!(
+            <div class="ed-line">
+                {for split_regions(regions, sels).into_iter().map(|(r, selected)| html!(
+                    <span title=r.title() style={if selected { format!("{}{}", r.css(), sel_sty) } else { r.css() }}>
+                        {text_to_html(r.text(), &mut tab_widths)}
+                    </span>
+                ))}
+                {if sels.iter().any(|&(_, to)| to > line_len) {
+                    html!(<span style=sel_sty.to_string()>{"\u{00a0}"}</span>)
+                } else {
+                    html!()
+                }}
+            </div>
+        )
+    }
+}
+
+// widths of the tabs of line `y`, measured by `content` so that tab stops don't depend on the
+// fonts of the regions
+fn tab_widths(content: &Content, y: usize, regions: &[Region]) -> Vec<usize> {
+    regions.iter().flat_map(|r| r.text().chars()).enumerate()
+        .filter(|&(_, c)| c == '\t')
+        .map(|(x, _)| content.width_of_line(y, x + 1) - content.width_of_line(y, x))
+        .collect()
+}
+
+// highlights of the char index ranges `matches` on the lines `first..last`, positioned on top
+// of the lines
+pub fn matches_to_html(content: &Content, matches: &[(usize, usize)], (first, last): (usize, usize)) -> Html {
+    let sty = format!("background-color: {};", find_highlight_color(&content.options.selected_theme));
+    let line_height = content.line_height();
+    let boxes = matches.iter().flat_map(|&(from, to)| {
+        let (from_y, to_y) = (content.char_to_line(from), content.char_to_line(to));
+        (from_y.max(first)..=to_y.min(last.saturating_sub(1))).map(move |y| {
+            let line_start = content.line_to_char(y);
+            let x_from = if y == from_y { from - line_start } else { 0 };
+            let x_to = if y == to_y { to - line_start } else { content.num_chars_of_line(y) };
+            (y, content.width_of_line(y, x_from), content.width_of_line(y, x_to))
+        })
+    });
+    html!(
+        {for boxes.map(|(y, left, right)| html!(
+            <div class="ed-match" style={format!("top: {}px; left: {}px; width: {}px; {}", y * line_height, left, right - left, sty)}></div>
+        ))}
+    )
+}
+
+// text of a region, its tabs take the next widths of `tab_widths`
+fn text_to_html<I: Iterator<Item = usize>>(text: &str, tab_widths: &mut I) -> Html {
+    html!(
+        {for text.split('\t').enumerate().map(|(i, piece)| {
+            let tab = if i > 0 {
+                let width = tab_widths.next().unwrap_or(0);
+                html!(<span class="ed-tab" style={format!("display: inline-block; width: {}px;", width)}></span>)
+            } else {
+                html!()
+            };
+            html!(<>{tab}{piece.replace(' ', "\u{00a0}")}</>)
+        })}
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use edix_core::controller::Controller;
+    use edix_core::content::RichContentOptions;
+    use edix_core::font_rules::FontRules;
+    use edix_core::fonts::default_font_profiles;
+    use edix_core::measure::CharWidths;
+
+    const SOURCE: &str = "\
+// returns the sum of all values that are larger than `min`
+fn sum_larger(values: &[u32], min: u32) -> u32 {
+\tvalues.iter().filter(|&&v| v > min).sum()
+}
+";
+
+    fn controller() -> Controller {
+        let options = RichContentOptions {
+            line_height: 20,
+            selected_syntax: "Rust".to_string(),
+            selected_theme: "InspiredGitHub".to_string(),
+            selected_font_profile: "Mixed".to_string(),
+            font_profiles: default_font_profiles(),
+            font_rules: FontRules::default(),
+            indent_unit: Default::default(),
+        };
+        Controller::new(&SOURCE.repeat(50), options, Box::new(CharWidths::monospace(0.6)))
+    }
+
+    fn all_props(controller: &Controller, (first, last): (usize, usize)) -> Vec<LineProps> {
+        let content = controller.get_content();
+        let sel_sty: Rc<str> = "".into();
+        let mut line_start = content.line_to_char(first);
+        (first..last).map(|y| {
+            let props = line_props(content, y, line_start, &controller.get_selections(), &sel_sty);
+            line_start += content.num_chars_of_line(y) + 1;
+            props
+        }).collect()
+    }
+
+    // lines whose components are re-rendered when updated with the current properties
+    fn changed_lines(lines: &mut [LineView], controller: &Controller, (first, last): (usize, usize)) -> Vec<usize> {
+        lines.iter_mut().zip(all_props(controller, (first, last))).zip(first..)
+            .filter_map(|((line, props), y)| if line.change(props) { Some(y) } else { None })
+            .collect()
+    }
+
+    #[test]
+    fn only_edited_lines_are_rendered() {
+        let mut controller = controller();
+        let visible = visible_lines(controller.get_content(), 40 * 20, 1000);
+        let mut lines: Vec<LineView> = all_props(&controller, visible).into_iter().map(|props| LineView { props }).collect();
+
+        // typing in the tab-indented line 42 doesn't change the tab widths of the other lines
+        controller.set_idx(controller.get_content().line_to_char(42) + 1, false);
+        assert!(changed_lines(&mut lines, &controller, visible).is_empty());
+        controller.key_char('x');
+        assert_eq!(changed_lines(&mut lines, &controller, visible), vec!(42));
+
+        // moving the cursor doesn't change any line
+        controller.cursor_right(false);
+        assert!(changed_lines(&mut lines, &controller, visible).is_empty());
+
+        // selecting only changes the lines of the selection
+        controller.cursor_down(true);
+        assert_eq!(changed_lines(&mut lines, &controller, visible), vec!(42, 43));
+    }
+}